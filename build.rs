@@ -0,0 +1,141 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").expect("set by cargo"));
+
+    embed_notification_sound(&manifest_dir);
+    embed_build_info(&manifest_dir);
+}
+
+/// Lets packagers embed their own notification sound, see the README.
+/// The sound is checked here, so that a file that can't be played fails the build instead of the first notification.
+fn embed_notification_sound(manifest_dir: &Path) {
+    println!("cargo:rerun-if-env-changed=CORRODORO_NOTIFICATION_SOUND");
+
+    // relative paths are relative to the manifest, absolute paths replace it when joined
+    let path = match env::var_os("CORRODORO_NOTIFICATION_SOUND") {
+        Some(path) if !path.is_empty() => manifest_dir.join(path),
+        _ => manifest_dir.join("media/notification.wav"),
+    };
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let sound = fs::read(&path).unwrap_or_else(|err| {
+        panic!(
+            "failed to read notification sound {}: {err}",
+            path.display()
+        )
+    });
+    if let Err(reason) = check_wav(&sound) {
+        panic!(
+            "notification sound {} can't be played: {reason}",
+            path.display()
+        );
+    }
+
+    println!(
+        "cargo:rustc-env=CORRODORO_NOTIFICATION_SOUND_PATH={}",
+        path.display()
+    );
+}
+
+/// For `--version-verbose`. Builds outside of a git checkout, e.g. from crates.io, have no commit.
+fn embed_build_info(manifest_dir: &Path) {
+    let git_dir = manifest_dir.join(".git");
+    if git_dir.exists() {
+        for path in ["HEAD", "refs/heads", "packed-refs"] {
+            println!("cargo:rerun-if-changed={}", git_dir.join(path).display());
+        }
+    }
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .current_dir(manifest_dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // reproducible builds set the date through SOURCE_DATE_EPOCH
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let build_time = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        });
+
+    println!("cargo:rustc-env=CORRODORO_GIT_COMMIT={commit}");
+    println!(
+        "cargo:rustc-env=CORRODORO_BUILD_DATE={}",
+        utc_date(build_time)
+    );
+    println!(
+        "cargo:rustc-env=CORRODORO_TARGET={}",
+        env::var("TARGET").expect("set by cargo")
+    );
+    println!(
+        "cargo:rustc-env=CORRODORO_PROFILE={}",
+        env::var("PROFILE").expect("set by cargo")
+    );
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD` in UTC, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn utc_date(timestamp: u64) -> String {
+    let days = (timestamp / (24 * 60 * 60)) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Only WAV is decoded, see the features of `rodio`. Checks the header and the sample format of the first `fmt ` chunk.
+fn check_wav(sound: &[u8]) -> Result<(), &'static str> {
+    if sound.len() < 12 || &sound[0..4] != b"RIFF" || &sound[8..12] != b"WAVE" {
+        return Err("not a WAV file");
+    }
+
+    let mut chunks = &sound[12..];
+    while chunks.len() >= 8 {
+        let id = &chunks[0..4];
+        let size = u32::from_le_bytes([chunks[4], chunks[5], chunks[6], chunks[7]]) as usize;
+        let body = &chunks[8..];
+
+        if id == b"fmt " {
+            if body.len() < 2 {
+                return Err("truncated format chunk");
+            }
+            const PCM: u16 = 1;
+            const IEEE_FLOAT: u16 = 3;
+            const EXTENSIBLE: u16 = 0xFFFE;
+            return match u16::from_le_bytes([body[0], body[1]]) {
+                PCM | IEEE_FLOAT | EXTENSIBLE => Ok(()),
+                _ => Err("compressed WAV files are not supported, use PCM samples"),
+            };
+        }
+
+        // chunks are padded to an even size
+        let skip = 8 + size + size % 2;
+        chunks = chunks.get(skip..).unwrap_or_default();
+    }
+
+    Err("missing format chunk")
+}
@@ -1,36 +1,227 @@
-use crate::pomodoro::State;
-use crate::protocol::{Event, NetworkProtocol, TimerVisuals};
-use crate::tui::{Tui, TuiError};
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsExporter;
+use crate::notification;
+use crate::pomodoro::{Activity, SessionDuration, State};
+use crate::protocol::{self, Adjustment, Event, NetworkProtocol, TimerVisuals};
+use crate::session_log::SessionLog;
+use crate::stats::{self, LifetimeStats};
+use crate::strings::strings;
+use crate::tui::{self, Action, Tui, TuiError};
+#[cfg(feature = "web")]
+use crate::web::WebView;
+use rate_limit::{Admission, RateLimiter};
+use room::Room;
+use servers::Servers;
+use std::collections::{HashMap, HashSet};
+use std::mem;
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::panic;
+use std::path::PathBuf;
 use std::task::Poll;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use thiserror::Error;
+use throttle::EventThrottle;
 use tokio::select;
-use tokio::time::{interval, Interval};
-use zwiesel::{Client, ClientError, ClientEvent, Server, ServerError, ServerEvent};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Instant, Interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+use zwiesel::{Client, ClientError, ClientEvent, ClientId, ServerError, ServerEvent};
+
+pub mod rate_limit;
+mod room;
+mod servers;
+mod throttle;
 
 pub struct App {
     pomodoro_state: State,
+    config: Config,
     tui: Tui,
-    server: Option<Server<NetworkProtocol>>,
+    server: Option<Servers>,
+    /// Named rooms with their own timers. Clients that haven't joined one share the host's timer.
+    rooms: HashMap<String, Room>,
+    client_rooms: HashMap<ClientId, String>,
+    /// Latest connection of every client that presented a session token, see `NetworkProtocol::Resume`.
+    /// Forgotten `App::SESSION_RETENTION` after the client disconnected.
+    session_tokens: HashMap<u64, Session>,
+    /// Connected clients that already presented a session token, which they may only do once.
+    resumed_clients: HashSet<ClientId>,
+    /// Connected clients that haven't sent anything yet. They are announced with their first message,
+    /// because only then it is known whether they are reconnecting.
+    unannounced_clients: HashSet<ClientId>,
+    /// Messages that clients weren't supposed to send, see `protocol_violation`.
+    protocol_violations: HashMap<ClientId, u32>,
+    /// Messages of every client, see `Config::client_events_per_second`.
+    rate_limiter: RateLimiter<ClientId>,
+    /// When to repeat the notification of the last ended activity, see `Config::renotify_after`.
+    renotify_at: Option<Instant>,
+    /// When the user was asked whether they are still there, cleared by any input.
+    checked_in_at: Option<Instant>,
+    /// When the host first tried to quit while clients were connected, see `Config::confirm_quit`.
+    quit_requested_at: Option<Instant>,
+    /// Whether clients were connected after the last server event, see `Config::pause_when_empty`.
+    had_clients: bool,
+    /// Set when the timer was paused because the last client left, so that only then it is resumed.
+    paused_when_empty: bool,
+    /// When the timer was first started, cleared on reset.
+    session_started_at: Option<Instant>,
+    session_log: Option<SessionLog>,
+    lifetime_stats: Option<LifetimeStats>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<MetricsExporter>,
+    #[cfg(feature = "web")]
+    web: Option<WebView>,
+}
+
+/// Options that change how the app behaves, as opposed to the pomodoro `Settings`.
+pub struct Config {
+    /// Interval between timer updates, see `TimerArgs::tick`.
+    pub tick_interval: Duration,
+    /// Redraw the clock between timer updates with interpolated progress.
+    pub smooth_animation: bool,
+    /// Pulse the border of the timer while it is paused, see `App::IDLE_ANIMATION_INTERVAL`.
+    pub idle_animation: bool,
+    /// Show the wall time since the session started.
+    pub show_elapsed: bool,
+    /// File that completed activities are appended to.
+    pub session_log: Option<PathBuf>,
+    /// When the user quits, keep serving clients without the tui until interrupted.
+    pub detach_ui: bool,
+    /// Only quit and disconnect connected clients after quitting a second time within `App::QUIT_CONFIRMATION_WINDOW`.
+    pub confirm_quit: bool,
+    /// Pause the timer when the last client disconnects, and resume it when a client connects again.
+    pub pause_when_empty: bool,
+    /// Add completed focus sessions to the lifetime statistics.
+    pub record_stats: bool,
+    /// Show the focus sessions completed today according to the lifetime statistics.
+    pub show_focus_sessions_today: bool,
+    /// Ignore the quit key while a focus session is running, see `Tui::set_quit_locked`.
+    pub focus_lock: bool,
+    /// Play a quiet sound every time another minute of focus has passed.
+    pub minute_chime: bool,
+    /// Repeat the notification of an ended activity once after this long, unless a key was pressed in the meantime.
+    pub renotify_after: Option<Duration>,
+    /// After how long without input a running focus session asks whether the user is still there,
+    /// and how long it waits for an answer before pausing the timer.
+    pub check_in: Option<(Duration, Duration)>,
+    /// Stop the app once this many focus sessions have been completed.
+    pub stop_after_focus_sessions: Option<u32>,
+    /// While the timer is paused, only broadcast a frame to clients if it differs from the last one.
+    pub silent_while_paused: bool,
+    /// Minimum time between two frames broadcast to clients, frames in between are skipped. `None` for no limit.
+    pub broadcast_interval: Option<Duration>,
+    /// Messages a client may send per second, further ones are dropped. Exceeding it is not a protocol violation,
+    /// a client may lag behind and send a burst of events at once.
+    pub client_events_per_second: u32,
+    pub notification_texts: NotificationTexts,
+    pub tui: tui::Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            tick_interval: Duration::from_millis(100),
+            smooth_animation: false,
+            idle_animation: false,
+            show_elapsed: false,
+            session_log: None,
+            detach_ui: false,
+            confirm_quit: false,
+            pause_when_empty: false,
+            record_stats: false,
+            show_focus_sessions_today: false,
+            focus_lock: false,
+            minute_chime: false,
+            renotify_after: None,
+            check_in: None,
+            stop_after_focus_sessions: None,
+            silent_while_paused: false,
+            broadcast_interval: None,
+            client_events_per_second: 30,
+            notification_texts: NotificationTexts::default(),
+            tui: tui::Config::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Longest time between two frames broadcast while the timer runs, see `NetworkProtocol::FrameInterval`.
+    /// The broadcast interval is only checked on ticks, so a frame may come up to a tick late.
+    fn frame_interval(&self) -> Duration {
+        self.tick_interval + self.broadcast_interval.unwrap_or_default()
+    }
 }
 
 impl App {
-    pub fn new(pomodoro_state: State) -> Result<Self, UnrecoverableError> {
-        let tui = Tui::new()?;
+    pub fn new(pomodoro_state: State, config: Config) -> Result<Self, UnrecoverableError> {
+        let tui = Tui::new(config.tui.clone())?;
+        Self::with_tui(pomodoro_state, config, tui)
+    }
+
+    fn with_tui(
+        pomodoro_state: State,
+        config: Config,
+        mut tui: Tui,
+    ) -> Result<Self, UnrecoverableError> {
+        // fail before the session starts rather than when the first activity completes
+        let session_log = match &config.session_log {
+            Some(path) => Some(
+                SessionLog::open(path)
+                    .map_err(|err| UnrecoverableError::SessionLog(path.clone(), err))?,
+            ),
+            None => None,
+        };
+        config.tui.sound_backend.warm_up();
+        // statistics are not worth failing over
+        let lifetime_stats = match config.record_stats.then(LifetimeStats::load) {
+            Some(Ok(stats)) => Some(stats),
+            Some(Err(err)) => {
+                tui.show_hint(&err.to_string());
+                None
+            }
+            None => None,
+        };
 
         Ok(Self {
             pomodoro_state,
+            rate_limiter: RateLimiter::new(config.client_events_per_second),
+            config,
             tui,
             server: None,
+            rooms: HashMap::new(),
+            client_rooms: HashMap::new(),
+            session_tokens: HashMap::new(),
+            resumed_clients: HashSet::new(),
+            unannounced_clients: HashSet::new(),
+            protocol_violations: HashMap::new(),
+            renotify_at: None,
+            checked_in_at: None,
+            quit_requested_at: None,
+            had_clients: false,
+            paused_when_empty: false,
+            session_started_at: None,
+            session_log,
+            lifetime_stats,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            #[cfg(feature = "web")]
+            web: None,
         })
     }
 
-    pub async fn start_server(&mut self, socket: SocketAddr) -> Result<(), ServerError> {
-        self.server = Some(Server::host(socket).await?);
+    /// Listens on all of the given sockets.
+    pub async fn start_server(&mut self, sockets: &[SocketAddr]) -> Result<(), UnrecoverableError> {
+        let server = Servers::host(sockets).await.map_err(|err| match err {
+            ServerError::Bind(socket, err)
+                if err.kind() == std::io::ErrorKind::PermissionDenied
+                    && socket.port() < PRIVILEGED_PORTS_END =>
+            {
+                UnrecoverableError::PrivilegedPort(socket.port(), err)
+            }
+            err => err.into(),
+        })?;
+        self.server = Some(server);
+
         Ok(())
     }
 
@@ -39,50 +230,293 @@ impl App {
             server.disconnect_all();
             self.server = None;
         }
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics = None;
+        }
+        #[cfg(feature = "web")]
+        {
+            self.web = None;
+        }
     }
 
-    pub async fn run(&mut self) -> Result<(), UnrecoverableError> {
+    /// Serves metrics about the session until the server is stopped.
+    #[cfg(feature = "metrics")]
+    pub async fn start_metrics(&mut self, socket: SocketAddr) -> Result<(), UnrecoverableError> {
+        self.metrics = Some(
+            MetricsExporter::host(socket)
+                .await
+                .map_err(|err| UnrecoverableError::MetricsBind(socket, err))?,
+        );
+        Ok(())
+    }
+
+    /// Serves a web view of the host's timer until the server is stopped.
+    #[cfg(feature = "web")]
+    pub async fn start_web(&mut self, socket: SocketAddr) -> Result<(), UnrecoverableError> {
+        self.web = Some(
+            WebView::host(socket, self.config.client_events_per_second)
+                .await
+                .map_err(|err| UnrecoverableError::WebBind(socket, err))?,
+        );
+        Ok(())
+    }
+
+    /// About 30 frames per second, see `Config::smooth_animation`.
+    const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_millis(33);
+    /// Time between two steps of the idle animation, slow enough to redraw only once per step.
+    const IDLE_ANIMATION_INTERVAL: Duration = Duration::from_secs(1);
+    /// Misbehaving clients are disconnected after this many unexpected messages.
+    const MAX_PROTOCOL_VIOLATIONS: u32 = 3;
+    /// As long as the hint that asks for the confirmation is shown.
+    const QUIT_CONFIRMATION_WINDOW: Duration = Duration::from_secs(3);
+    /// How long a disconnected client can still reconnect as the same client, see `NetworkProtocol::Resume`.
+    const SESSION_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+    pub async fn run(&mut self) -> Result<RunOutcome, UnrecoverableError> {
         self.tui.enable()?;
-        let maybe_err = self.run_inner().await;
+        let result = self.run_inner().await;
         self.tui.disable()?;
 
-        maybe_err?;
-        Ok(())
+        result
     }
 
-    async fn run_inner(&mut self) -> Result<(), UnrecoverableError> {
-        let mut pomodoro_clock = interval(Duration::from_millis(100));
+    async fn run_inner(&mut self) -> Result<RunOutcome, UnrecoverableError> {
+        let mut pomodoro_clock = interval(self.config.tick_interval);
         let mut pomodoro_start_time = Instant::now();
+        let mut last_broadcast_visuals = None;
+        let mut last_broadcast_at: Option<Instant> = None;
+        let mut animation_clock = interval(Self::ANIMATION_FRAME_INTERVAL);
+        // the animation is not drawn while the timer is paused, don't catch up on the frames afterwards
+        animation_clock.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut idle_clock = interval(Self::IDLE_ANIMATION_INTERVAL);
+        idle_clock.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut idle_frame: u64 = 0;
+        // set when the loop only woke up to redraw the animation, nothing has changed for clients then
+        let mut is_animation_frame = false;
+        // the pomodoro clock is stopped while nothing changes without an event, see `needs_ticks`
+        let mut is_ticking = true;
 
-        loop {
-            let visuals = TimerVisuals::from(&*self);
+        let outcome = loop {
+            if let Some(goal) = self.config.stop_after_focus_sessions {
+                if self.pomodoro_state.completed_focus_sessions() >= goal {
+                    break RunOutcome::Completed;
+                }
+            }
+
+            // nobody needs to answer once the focus session ended or was paused
+            if self.checked_in_at.is_some() && self.check_in_due_at().is_none() {
+                self.checked_in_at = None;
+                self.tui.set_check_in_shown(false);
+            }
+            let visuals = TimerVisuals::from(&self.pomodoro_state);
             let network_status = NetworkStatus::from(&*self);
-            self.tui.render(&visuals, &network_status)?;
-            if let Some(server) = &mut self.server {
-                let _ = server.broadcast(&NetworkProtocol::Display(visuals)).await?;
+            if self.config.show_elapsed {
+                self.tui
+                    .set_session_elapsed(self.session_started_at.map(|t| t.elapsed()));
+            }
+            if let Some(goal) = self.config.stop_after_focus_sessions {
+                self.tui.set_done_by(done_by(&self.pomodoro_state, goal));
+            }
+            if self.config.show_focus_sessions_today {
+                self.tui.set_focus_sessions_today(
+                    self.lifetime_stats
+                        .as_ref()
+                        .map(LifetimeStats::focus_sessions_today),
+                );
+            }
+            let all_time_ratio = self
+                .lifetime_stats
+                .as_ref()
+                .map(|stats| stats::focus_break_ratio(stats.focus_time, stats.break_time));
+            self.tui
+                .set_focus_break_ratio(Some((strings().focus_break_ratio)(
+                    &stats::focus_break_ratio(
+                        self.pomodoro_state.focus_time(),
+                        self.pomodoro_state.break_time(),
+                    ),
+                    all_time_ratio.as_deref(),
+                )));
+            if self.config.focus_lock {
+                self.tui.set_quit_locked(
+                    self.pomodoro_state.current_activity().is_focus()
+                        && self.pomodoro_state.timer_is_active(),
+                );
+            }
+            let mut rendered_visuals = visuals;
+            if self.config.smooth_animation && self.pomodoro_state.timer_is_active() {
+                rendered_visuals.progress_percentage = self
+                    .pomodoro_state
+                    .progress_percentage_after(pomodoro_start_time.elapsed());
+            }
+            if self.config.idle_animation {
+                self.tui
+                    .set_idle_dimmed(visuals.timer_is_paused && idle_frame % 2 == 1);
+            }
+            self.tui.render(&rendered_visuals, &network_status)?;
+            // animation frames only change the local clock, and without clients nobody would receive the frames.
+            // new clients are sent a frame when they connect
+            let broadcast_is_due = match (self.config.broadcast_interval, last_broadcast_at) {
+                (Some(broadcast_interval), Some(at)) => at.elapsed() >= broadcast_interval,
+                _ => true,
+            };
+            let needs_broadcast = !mem::take(&mut is_animation_frame) && broadcast_is_due;
+            if let Some(server) = self.server.as_mut().filter(|_| needs_broadcast) {
+                let broadcast = broadcast_frames(
+                    server,
+                    &self.client_rooms,
+                    &mut self.rooms,
+                    visuals,
+                    &mut last_broadcast_visuals,
+                    self.config.silent_while_paused,
+                )
+                .await?;
+                if broadcast {
+                    last_broadcast_at = Some(Instant::now());
+                }
             }
+            #[cfg(feature = "metrics")]
+            if let (Some(metrics), Some(server)) = (&self.metrics, &self.server) {
+                metrics.update(&visuals, server.clients().len());
+            }
+            #[cfg(feature = "web")]
+            if let Some(web) = &self.web {
+                web.update(&visuals);
+            }
+
+            let needs_ticks = self.needs_ticks();
+            if needs_ticks && !is_ticking {
+                // don't catch up on the ticks missed while idle
+                pomodoro_clock.reset();
+            }
+            is_ticking = needs_ticks;
 
             select! {
-                _ = pomodoro_clock.tick() => {
+                _ = tokio::signal::ctrl_c(), if self.tui.is_detached() => {
+                    break RunOutcome::Quit;
+                }
+                _ = animation_clock.tick(), if self.config.smooth_animation && self.pomodoro_state.timer_is_active() => {
+                    is_animation_frame = true;
+                }
+                _ = idle_clock.tick(), if self.config.idle_animation && !self.pomodoro_state.timer_is_active() => {
+                    idle_frame += 1;
+                    is_animation_frame = true;
+                }
+                _ = pomodoro_clock.tick(), if is_ticking => {
+                    if let Some(server) = &mut self.server {
+                        let now = Instant::now();
+                        for (name, room) in &mut self.rooms {
+                            if let Some(activity) = room.tick(now) {
+                                send_to_room(server, &self.client_rooms, Some(name), &NetworkProtocol::Notify(activity)).await?;
+                            }
+                        }
+                    }
+
                     if self.pomodoro_state.timer_is_active() {
                         let activity_before = self.pomodoro_state.current_activity();
+                        let duration_before = self.pomodoro_state.current_activity_duration();
+                        let minutes_before = elapsed_minutes(&self.pomodoro_state);
 
                         self.pomodoro_state.increase_progress(pomodoro_start_time.elapsed());
                         pomodoro_start_time = Instant::now();
 
+                        // compared per tick, a tick that skips several minutes still only chimes once
+                        if self.config.minute_chime
+                            && activity_before.is_focus()
+                            && self.pomodoro_state.current_activity() == activity_before
+                            && elapsed_minutes(&self.pomodoro_state) > minutes_before
+                        {
+                            self.tui.play_chime();
+                        }
+
+                        // with long tick intervals, wake up early so that the displayed seconds don't lag behind
+                        let displayed_countdown = self.pomodoro_state.preparation_remaining().unwrap_or(*self.pomodoro_state.time_remaining());
+                        let until_displayed_second_changes = match displayed_countdown.subsec_nanos() {
+                            0 => Duration::from_secs(1),
+                            nanos => Duration::from_nanos(nanos.into()),
+                        };
+                        if until_displayed_second_changes < self.config.tick_interval {
+                            pomodoro_clock.reset_after(until_displayed_second_changes);
+                        }
+
                         let activity_after = self.pomodoro_state.current_activity();
                         if activity_before != activity_after {
+                            info!(completed = %activity_before, next = %activity_after, "activity completed");
+                            if let Some(session_log) = &mut self.session_log {
+                                if let Err(err) = session_log.record(activity_before, *duration_before) {
+                                    warn!(%err, "failed to write the session log");
+                                    self.tui.show_hint(strings().session_log_write_failed);
+                                }
+                            }
+                            if let Some(stats) = &mut self.lifetime_stats {
+                                match activity_before {
+                                    Activity::Focus => stats.record_focus_session(*duration_before),
+                                    Activity::ShortBreak | Activity::LongBreak => stats.record_break(*duration_before),
+                                }
+                                if let Err(err) = stats.save() {
+                                    warn!(%err, "failed to save the statistics");
+                                    self.tui.show_hint(&err.to_string());
+                                }
+                            }
                             if let Some(server) = &mut self.server {
-                                let _ = server.broadcast(&NetworkProtocol::Notify(activity_after)).await?;
+                                send_to_room(server, &self.client_rooms, None, &NetworkProtocol::Notify(activity_after)).await?;
                             }
-                            self.tui.show_notification(&activity_after.to_string(), true);
+                            self.tui.show_notification(&self.config.notification_texts.completion(activity_after), true);
+                            self.renotify_at = self.config.renotify_after.map(|after| Instant::now() + after);
                         }
                     }
                 }
-                tui_event = self.tui.read_event() => {
-                    let event = tui_event?;
-                    if *self.handle_event(&event, &mut pomodoro_clock, &mut pomodoro_start_time) {
-                        break;
+                _ = tokio::time::sleep_until(self.renotify_at.unwrap_or_else(Instant::now)), if self.renotify_at.is_some() => {
+                    self.renotify_at = None;
+                    // nobody needs a reminder once the next activity runs
+                    if !self.pomodoro_state.timer_is_active() {
+                        let activity = self.pomodoro_state.current_activity();
+                        self.tui.show_notification(&self.config.notification_texts.completion(activity), true);
+                    }
+                }
+                _ = tokio::time::sleep_until(self.check_in_due_at().unwrap_or_else(Instant::now)), if self.check_in_due_at().is_some() => {
+                    let last_input_at = Instant::from_std(self.tui.last_input_at());
+                    match self.checked_in_at {
+                        // answered with a key that only the tui handled
+                        Some(checked_in_at) if last_input_at > checked_in_at => self.checked_in_at = None,
+                        Some(_) => {
+                            self.checked_in_at = None;
+                            self.tui.set_check_in_shown(false);
+                            info!("paused the timer, nobody answered the check-in");
+                            self.handle_event(&Event::ToggleTimer, None, &mut pomodoro_clock, &mut pomodoro_start_time).await?;
+                            self.tui.show_notification(strings().paused_after_check_in, false);
+                        }
+                        // input since the deadline was set, it moved on
+                        None if self.check_in_due_at().is_some_and(|due_at| due_at > Instant::now()) => {}
+                        None => {
+                            self.checked_in_at = Some(Instant::now());
+                            self.tui.set_check_in_shown(true);
+                            self.tui.show_notification(strings().still_focusing, true);
+                        }
+                    }
+                }
+                tui_action = self.tui.read_event() => {
+                    // any key acknowledges the ended activity and answers the check-in, see `Tui::last_input_at`
+                    self.renotify_at = None;
+                    self.checked_in_at = None;
+                    match tui_action? {
+                        Action::Event(event) => {
+                            if *self.handle_event(&event, None, &mut pomodoro_clock, &mut pomodoro_start_time).await? {
+                                break RunOutcome::Quit;
+                            }
+                        }
+                        Action::DisconnectClient(client_id) => self.disconnect_client(client_id),
+                    }
+                }
+                web_event = async {
+                    #[cfg(feature = "web")]
+                    if let Some(web) = &mut self.web {
+                        return web.event().await;
+                    }
+                    ForeverPending.await.forever()
+                } => {
+                    if *self.handle_event(&web_event, None, &mut pomodoro_clock, &mut pomodoro_start_time).await? {
+                        break RunOutcome::Quit;
                     }
                 }
                 server_event = async {
@@ -91,92 +525,634 @@ impl App {
                         _ => ForeverPending.await.forever(),
                     }
                 } => {
+                    if let Err(err) = &server_event {
+                        // ignore network errors for now
+                        warn!(%err, "server error");
+                    }
                     if let Ok(event) = server_event {
+                        if let ServerEvent::Message(client_id, message) = &event {
+                            if !matches!(message, NetworkProtocol::Resume(_)) && self.unannounced_clients.remove(client_id) {
+                                self.tui.show_notification(&(strings().client_connected)(&client_id.to_string()), false);
+                            }
+                        }
                         match event {
                             ServerEvent::NewConnection(client_id) => {
-                                self.tui.show_notification(&format!("Client {client_id} connected"), false);
+                                info!(client = %client_id, "client connected");
+                                let visuals = TimerVisuals::from(&self.pomodoro_state);
+                                if let Some(server) = &mut self.server {
+                                    // the client may not receive a broadcast for a while, e.g. while the timer is paused
+                                    if let Err(err) = server.send(client_id, &NetworkProtocol::Display(visuals)).await {
+                                        warn!(client = %client_id, %err, "failed to send the first frame");
+                                    }
+                                    if let Err(err) = server.send(client_id, &NetworkProtocol::FrameInterval(self.config.frame_interval())).await {
+                                        warn!(client = %client_id, %err, "failed to send the frame interval");
+                                    }
+                                }
+                                self.unannounced_clients.insert(client_id);
+                            }
+                            // clients that never said anything aren't announced when they leave either
+                            ServerEvent::Disconnect(client_id, _) if self.unannounced_clients.remove(&client_id) => {
+                                info!(client = %client_id, "unannounced client disconnected");
                             }
                             ServerEvent::Disconnect(client_id, None) => {
-                                self.tui.show_notification(&format!("Client {client_id} disconnected"), false);
+                                info!(client = %client_id, "client disconnected");
+                                self.tui.show_notification(&(strings().client_disconnected)(&client_id.to_string()), false);
+                            }
+                            ServerEvent::Disconnect(client_id, Some(err)) => {
+                                warn!(client = %client_id, %err, "client disconnected after a network error");
+                                self.tui.show_notification(&(strings().client_disconnected_network_error)(&client_id.to_string()), false);
                             }
-                            ServerEvent::Disconnect(client_id, Some(_)) => {
-                                self.tui.show_notification(&format!("Client {client_id} disconnected because of a network error"), false);
+                            ServerEvent::Message(client_id, _) if !self.admit_message(client_id) => {}
+                            ServerEvent::Message(client_id, NetworkProtocol::Resume(_)) if self.resumed_clients.contains(&client_id) => {
+                                self.protocol_violation(client_id, "presented a session token twice");
                             }
-                            ServerEvent::Message(_, message) => {
-                                match message {
-                                    NetworkProtocol::Event(event) => {
-                                        if *self.handle_event(&event, &mut pomodoro_clock, &mut pomodoro_start_time) {
-                                            break;
+                            ServerEvent::Message(client_id, NetworkProtocol::Resume(token)) => {
+                                self.resumed_clients.insert(client_id);
+                                let previous_session = self.session_tokens.get(&token).map(|session| (session.client_id, session.disconnected_at.is_none()));
+                                let previous_client_id = match previous_session {
+                                    // the session is still in use, it can't be taken over
+                                    Some((owner, true)) => {
+                                        warn!(client = %client_id, %owner, "client presented the session token of a connected client");
+                                        None
+                                    }
+                                    previous_session => {
+                                        self.session_tokens.insert(token, Session { client_id, disconnected_at: None });
+                                        previous_session.map(|(previous_client_id, _)| previous_client_id)
+                                    }
+                                };
+                                debug!(client = %client_id, previous_client = ?previous_client_id.map(|id| id.to_string()), "client resumed its session");
+                                if self.unannounced_clients.remove(&client_id) {
+                                    match previous_client_id {
+                                        // a hint instead of a notification, the client was only gone for a moment
+                                        Some(previous_client_id) => self.tui.show_hint(&(strings().client_reconnected)(&client_id.to_string(), &previous_client_id.to_string())),
+                                        None => self.tui.show_notification(&(strings().client_connected)(&client_id.to_string()), false),
+                                    }
+                                }
+                            }
+                            // only the host may quit or disconnect clients
+                            ServerEvent::Message(client_id, NetworkProtocol::Event(Event::Quit | Event::Announce(_))) => {
+                                self.protocol_violation(client_id, "sent an event only the host may send");
+                            }
+                            ServerEvent::Message(client_id, NetworkProtocol::Event(Event::SetProgress(fraction))) if !fraction.is_finite() => {
+                                self.protocol_violation(client_id, "sent a progress that is not a number");
+                            }
+                            ServerEvent::Message(client_id, NetworkProtocol::JoinRoom(name)) => {
+                                self.join_room(client_id, name).await?;
+                            }
+                            ServerEvent::Message(client_id, NetworkProtocol::RequestSync) => {
+                                let visuals = match self.client_rooms.get(&client_id) {
+                                    Some(name) => TimerVisuals::from(&self.rooms[name].state),
+                                    None => TimerVisuals::from(&self.pomodoro_state),
+                                };
+                                if let Some(server) = &mut self.server {
+                                    if let Err(err) = server.send(client_id, &NetworkProtocol::Display(visuals)).await {
+                                        warn!(client = %client_id, %err, "failed to send a requested frame");
+                                    }
+                                }
+                            }
+                            ServerEvent::Message(client_id, NetworkProtocol::Event(event)) => {
+                                if let Some(name) = self.client_rooms.get(&client_id) {
+                                    let room = self.rooms.get_mut(name).expect("rooms of clients exist");
+                                    let time_remaining_before = room.state.time_remaining();
+                                    let skipped_to = room.handle_event(&event, Instant::now());
+                                    let adjustment = adjustment(&event, time_remaining_before, room.state.time_remaining(), &room.state, Some(client_id));
+                                    if let Some(server) = &mut self.server {
+                                        if let Some(activity) = skipped_to {
+                                            send_to_room(server, &self.client_rooms, Some(name), &NetworkProtocol::NotifySkip(activity)).await?;
+                                        }
+                                        if let Some(adjustment) = adjustment {
+                                            send_to_room(server, &self.client_rooms, Some(name), &NetworkProtocol::NotifyAdjustment(adjustment)).await?;
                                         }
                                     }
-                                    _ => (), // received wrong type of message, ignore for now
+                                } else if *self.handle_event(&event, Some(client_id), &mut pomodoro_clock, &mut pomodoro_start_time).await? {
+                                    break RunOutcome::Quit;
                                 }
                             }
+                            ServerEvent::Message(client_id, _) => {
+                                self.protocol_violation(client_id, "sent a message only the host may send");
+                            }
                         }
-                    } else {
-                        // ignore network errors for now
+                    }
+                    self.prune_rooms();
+                    if self.config.pause_when_empty {
+                        self.pause_when_empty(&mut pomodoro_clock, &mut pomodoro_start_time).await?;
                     }
                 }
             }
-        }
+        };
 
         if let Some(server) = &mut self.server {
             server.disconnect_all();
         }
 
-        Ok(())
+        Ok(outcome)
     }
 
-    fn handle_event(
+    /// `sender` is the client that sent the event, `None` if it came from the host's tui.
+    async fn handle_event(
         &mut self,
         event: &Event,
+        sender: Option<ClientId>,
         pomodoro_clock: &mut Interval,
         pomodoro_start_time: &mut Instant,
-    ) -> AppShouldQuit {
+    ) -> Result<AppShouldQuit, UnrecoverableError> {
         let timer_was_stopped = !self.pomodoro_state.timer_is_active();
 
         match event {
-            Event::ToggleTimer => {
-                self.pomodoro_state.toggle_timer();
+            Event::Announce(text) => {
+                if let Some(server) = &mut self.server {
+                    info!(text, "announcing");
+                    send_to_all(server, &NetworkProtocol::Announce(text.clone())).await?;
+                    self.tui.show_hint(&(strings().announced)(text));
+                }
             }
-            Event::ExtendActivity(duration) => {
-                self.pomodoro_state.extend_activity(duration);
+            Event::Quit if self.config.detach_ui && self.server.is_some() => {
+                self.tui.detach()?;
+                let port = self
+                    .server
+                    .as_ref()
+                    .and_then(|server| server.local_addrs().first().map(SocketAddr::port));
+                println!("{}", (strings().ui_detached)(port.unwrap_or_default()));
             }
-            Event::ReduceActivity(duration) => {
-                self.pomodoro_state.reduce_activity(duration);
+            Event::Quit if self.config.confirm_quit && !self.quit_confirmed() => {
+                let clients = self
+                    .server
+                    .as_ref()
+                    .map_or(0, |server| server.clients().len());
+                self.tui.show_hint(&(strings().confirm_quit)(clients));
             }
-            Event::SkipActivity => {
-                self.pomodoro_state.skip_activity();
+            Event::Quit => return Ok(AppShouldQuit(true)),
+            Event::ExtendActivity(duration)
+                if !self.pomodoro_state.can_extend_activity(duration) =>
+            {
+                let max_duration = self.pomodoro_state.settings().max_activity_duration;
+                self.tui
+                    .show_hint(&(strings().activity_too_long)(&max_duration.to_string()));
             }
-            Event::ResetTimer => {
-                self.pomodoro_state.reset();
+            Event::ExtendActivityPermanently(duration)
+                if !self
+                    .pomodoro_state
+                    .can_extend_activity_permanently(duration) =>
+            {
+                let max_duration = self.pomodoro_state.settings().max_activity_duration;
+                self.tui
+                    .show_hint(&(strings().activity_too_long)(&max_duration.to_string()));
+            }
+            timer_event => {
+                let time_remaining_before = self.pomodoro_state.time_remaining();
+                if apply_timer_event(&mut self.pomodoro_state, timer_event).is_some() {
+                    self.notify_skip().await?;
+                }
+                let time_remaining_after = self.pomodoro_state.time_remaining();
+                if let Some(adjustment) = adjustment(
+                    timer_event,
+                    time_remaining_before,
+                    time_remaining_after,
+                    &self.pomodoro_state,
+                    sender,
+                ) {
+                    self.tui.show_hint(&adjustment.text());
+                    if let Some(server) = &mut self.server {
+                        let message = NetworkProtocol::NotifyAdjustment(adjustment);
+                        send_to_room(server, &self.client_rooms, None, &message).await?;
+                    }
+                }
             }
-            Event::Quit => return AppShouldQuit(true),
         };
 
         let timer_is_active_now = self.pomodoro_state.timer_is_active();
         if timer_was_stopped && timer_is_active_now {
             pomodoro_clock.reset();
             *pomodoro_start_time = Instant::now();
+            self.session_started_at.get_or_insert(*pomodoro_start_time);
+        }
+        if let Event::ResetTimer = event {
+            self.session_started_at = None;
         }
 
-        AppShouldQuit(false)
+        Ok(AppShouldQuit(false))
+    }
+
+    async fn notify_skip(&mut self) -> Result<(), UnrecoverableError> {
+        let activity_now = self.pomodoro_state.current_activity();
+        if let Some(server) = &mut self.server {
+            send_to_room(
+                server,
+                &self.client_rooms,
+                None,
+                &NetworkProtocol::NotifySkip(activity_now),
+            )
+            .await?;
+        }
+        self.tui
+            .show_notification(&skip_notification_text(activity_now), false);
+
+        Ok(())
+    }
+
+    /// Moves a client into a room, creating the room with the host's settings if needed.
+    /// An empty name moves the client back to the host's timer.
+    async fn join_room(&mut self, client_id: ClientId, name: String) -> Result<(), ServerError> {
+        let visuals = if name.is_empty() {
+            self.client_rooms.remove(&client_id);
+            TimerVisuals::from(&self.pomodoro_state)
+        } else {
+            let settings = self.pomodoro_state.settings().clone();
+            let now = Instant::now();
+            let room = self
+                .rooms
+                .entry(name.clone())
+                .or_insert_with(|| Room::new(settings, now));
+            let visuals = TimerVisuals::from(&room.state);
+            self.client_rooms.insert(client_id, name);
+            visuals
+        };
+
+        if let Some(server) = &mut self.server {
+            // the room's next broadcast may be a while away, e.g. while its timer is paused
+            if let Err(err) = server
+                .send(client_id, &NetworkProtocol::Display(visuals))
+                .await
+            {
+                warn!(client = %client_id, %err, "failed to send the room's frame");
+            }
+        }
+        self.prune_rooms();
+
+        Ok(())
+    }
+
+    /// Pauses the timer when the last client left and resumes it when one is back, see `Config::pause_when_empty`.
+    /// Clients that time out or reconnect are handled alike, they are disconnected and connect again as a new client.
+    /// Leaves the timer alone if the host paused or resumed it in between.
+    async fn pause_when_empty(
+        &mut self,
+        pomodoro_clock: &mut Interval,
+        pomodoro_start_time: &mut Instant,
+    ) -> Result<(), UnrecoverableError> {
+        let has_clients = self.server.as_ref().is_some_and(Servers::has_clients);
+        let had_clients = mem::replace(&mut self.had_clients, has_clients);
+        let timer_is_active = self.pomodoro_state.timer_is_active();
+
+        match (had_clients, has_clients) {
+            (true, false) if timer_is_active => {
+                info!("paused the timer, the last client left");
+                self.handle_event(
+                    &Event::ToggleTimer,
+                    None,
+                    pomodoro_clock,
+                    pomodoro_start_time,
+                )
+                .await?;
+                self.paused_when_empty = true;
+                self.tui.show_hint(strings().paused_when_empty);
+            }
+            (false, true) if mem::take(&mut self.paused_when_empty) && !timer_is_active => {
+                info!("resumed the timer, a client is back");
+                self.handle_event(
+                    &Event::ToggleTimer,
+                    None,
+                    pomodoro_clock,
+                    pomodoro_start_time,
+                )
+                .await?;
+                self.tui.show_hint(strings().resumed_with_client);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// When to ask whether the user is still there, or to pause if they were asked already, see `Config::check_in`.
+    /// `None` unless a focus session is running.
+    fn check_in_due_at(&self) -> Option<Instant> {
+        let (after, grace) = self.config.check_in?;
+        if !self.pomodoro_state.current_activity().is_focus()
+            || !self.pomodoro_state.timer_is_active()
+        {
+            return None;
+        }
+
+        Some(match self.checked_in_at {
+            Some(checked_in_at) => checked_in_at + grace,
+            None => Instant::from_std(self.tui.last_input_at()) + after,
+        })
+    }
+
+    /// Whether the display or the clients change without an event. If not, e.g. while the timer is paused
+    /// and nobody is connected, the app sleeps until the next event instead of waking up on every tick.
+    fn needs_ticks(&self) -> bool {
+        needs_ticks(
+            &self.config,
+            &self.pomodoro_state,
+            self.session_started_at.is_some(),
+            self.server.as_ref().is_some_and(Servers::has_clients),
+            self.tui.has_hint(),
+        )
+    }
+
+    /// Whether the host may quit without disconnecting anyone by surprise, see `Config::confirm_quit`.
+    /// Otherwise, the next quit within `QUIT_CONFIRMATION_WINDOW` is confirmed.
+    fn quit_confirmed(&mut self) -> bool {
+        let has_clients = self
+            .server
+            .as_ref()
+            .is_some_and(|server| !server.clients().is_empty());
+        if !has_clients {
+            return true;
+        }
+        let confirmed = self
+            .quit_requested_at
+            .is_some_and(|requested_at| requested_at.elapsed() < Self::QUIT_CONFIRMATION_WINDOW);
+        self.quit_requested_at = Some(Instant::now());
+
+        confirmed
+    }
+
+    /// Asked for from the client list of the host's tui.
+    fn disconnect_client(&mut self, client_id: ClientId) {
+        if let Some(server) = &mut self.server {
+            info!(client = %client_id, "disconnecting client");
+            if let Err(err) = server.disconnect(client_id) {
+                warn!(client = %client_id, %err, "failed to disconnect client");
+            }
+            self.tui.show_notification(
+                &(strings().client_was_disconnected)(&client_id.to_string()),
+                false,
+            );
+        }
+    }
+
+    /// Ignores a message a client wasn't supposed to send. A client that keeps sending them is buggy or malicious,
+    /// it is disconnected after `MAX_PROTOCOL_VIOLATIONS`.
+    fn protocol_violation(&mut self, client_id: ClientId, reason: &str) {
+        let violations = self.protocol_violations.entry(client_id).or_default();
+        *violations += 1;
+        warn!(client = %client_id, violations, "client {reason}");
+        if *violations < Self::MAX_PROTOCOL_VIOLATIONS {
+            return;
+        }
+
+        if let Some(server) = &mut self.server {
+            warn!(client = %client_id, "disconnecting client for violating the protocol");
+            if let Err(err) = server.disconnect(client_id) {
+                warn!(client = %client_id, %err, "failed to disconnect client");
+            }
+            self.tui.show_hint(&(strings().client_violated_protocol)(
+                &client_id.to_string(),
+            ));
+        }
+    }
+
+    /// Whether a message of the client should be handled, see `Config::client_events_per_second`.
+    fn admit_message(&mut self, client_id: ClientId) -> bool {
+        match self.rate_limiter.admit(client_id, Instant::now()) {
+            Admission::Allowed => true,
+            Admission::StartedDropping => {
+                warn!(client = %client_id, "client sent too many messages");
+                false
+            }
+            Admission::Dropped => false,
+        }
+    }
+
+    /// Forgets clients that are no longer connected, expired sessions and rooms that are empty.
+    fn prune_rooms(&mut self) {
+        let connected_clients = match &self.server {
+            Some(server) => server.clients(),
+            None => vec![],
+        };
+        self.client_rooms
+            .retain(|client_id, _| connected_clients.contains(client_id));
+        self.protocol_violations
+            .retain(|client_id, _| connected_clients.contains(client_id));
+        self.rate_limiter.retain(&connected_clients);
+        self.resumed_clients
+            .retain(|client_id| connected_clients.contains(client_id));
+        let now = Instant::now();
+        for session in self.session_tokens.values_mut() {
+            if session.disconnected_at.is_none() && !connected_clients.contains(&session.client_id)
+            {
+                session.disconnected_at = Some(now);
+            }
+        }
+        self.session_tokens.retain(|_, session| {
+            session
+                .disconnected_at
+                .is_none_or(|disconnected_at| now - disconnected_at < Self::SESSION_RETENTION)
+        });
+        self.rooms
+            .retain(|name, _| self.client_rooms.values().any(|room| room == name));
     }
 }
 
+/// Applies an event that only affects the timer, other events are ignored.
+/// Returns the new activity if it was skipped to.
+fn apply_timer_event(state: &mut State, event: &Event) -> Option<Activity> {
+    match event {
+        Event::ToggleTimer => state.toggle_timer(),
+        Event::ExtendActivity(duration) => state.extend_activity(duration),
+        Event::ReduceActivity(duration) => state.reduce_activity(duration),
+        Event::ExtendActivityPermanently(duration) => state.extend_activity_permanently(duration),
+        Event::ReduceActivityPermanently(duration) => state.reduce_activity_permanently(duration),
+        Event::SetProgress(fraction) => state.set_progress_fraction(*fraction),
+        Event::SkipActivity => {
+            state.skip_activity();
+            return Some(state.current_activity());
+        }
+        Event::EndFocusEarly => {
+            if state.current_activity().is_focus() {
+                state.end_focus_early();
+                return Some(state.current_activity());
+            }
+        }
+        Event::ResetTimer => state.reset(),
+        Event::Quit | Event::RequestSync | Event::Announce(_) => (),
+    }
+
+    None
+}
+
+/// Sends a message to all clients in a room, `None` being the host's timer.
+/// Only errors that affect every client are returned, errors of single clients mean that they are no longer connected.
+async fn send_to_room(
+    server: &mut Servers,
+    client_rooms: &HashMap<ClientId, String>,
+    room: Option<&str>,
+    message: &NetworkProtocol,
+) -> Result<(), ServerError> {
+    for client_id in server.clients() {
+        if client_rooms.get(&client_id).map(String::as_str) != room {
+            continue;
+        }
+        match server.send(client_id, message).await {
+            Err(err @ ServerError::Message(_)) => return Err(err),
+            Err(err) => debug!(client = %client_id, %err, "failed to send to a client"),
+            Ok(()) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the frames of the host's timer and of every room to their clients, see `Config::silent_while_paused`.
+/// Without clients, no frames are built and `false` is returned.
+async fn broadcast_frames(
+    server: &mut Servers,
+    client_rooms: &HashMap<ClientId, String>,
+    rooms: &mut HashMap<String, Room>,
+    visuals: TimerVisuals,
+    last_broadcast_visuals: &mut Option<TimerVisuals>,
+    silent_while_paused: bool,
+) -> Result<bool, ServerError> {
+    if !server.has_clients() {
+        return Ok(false);
+    }
+
+    let is_silent =
+        silent_while_paused && visuals.timer_is_paused && *last_broadcast_visuals == Some(visuals);
+    if !is_silent {
+        send_to_room(
+            server,
+            client_rooms,
+            None,
+            &NetworkProtocol::Display(visuals),
+        )
+        .await?;
+        *last_broadcast_visuals = Some(visuals);
+    }
+
+    for (name, room) in rooms {
+        let visuals = TimerVisuals::from(&room.state);
+        let is_silent = silent_while_paused
+            && visuals.timer_is_paused
+            && room.last_broadcast_visuals == Some(visuals);
+        if !is_silent {
+            send_to_room(
+                server,
+                client_rooms,
+                Some(name),
+                &NetworkProtocol::Display(visuals),
+            )
+            .await?;
+            room.last_broadcast_visuals = Some(visuals);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Sends to every client regardless of its room.
+async fn send_to_all(server: &mut Servers, message: &NetworkProtocol) -> Result<(), ServerError> {
+    for client_id in server.clients() {
+        match server.send(client_id, message).await {
+            Err(err @ ServerError::Message(_)) => return Err(err),
+            Err(err) => debug!(client = %client_id, %err, "failed to send to a client"),
+            Ok(()) => {}
+        }
+    }
+
+    Ok(())
+}
+
 pub struct ClientApp {
     tui: Tui,
     client: Client<NetworkProtocol>,
+    /// Shown as the address connected to, see `connect`.
+    host_addr: SocketAddr,
+    /// Sent to the host on every connection, see `NetworkProtocol::Resume`.
+    session_token: u64,
+    room: Option<String>,
+    notification_texts: NotificationTexts,
+    /// The last frame and when it was received.
+    last_frame: Option<(TimerVisuals, Instant)>,
+    /// How long the running timer of the host may go without a frame, see `stale_after`.
+    stale_after: Duration,
+    /// Set while the connection is lost, see `Reconnection`.
+    reconnection: Option<Reconnection>,
+    /// The attempt to reconnect that is in progress, it runs in the background so that the tui stays responsive.
+    connecting: Option<JoinHandle<Option<Client<NetworkProtocol>>>>,
+    /// The host sending messages only clients send is only pointed out once.
+    protocol_violation_shown: bool,
+    throttle: EventThrottle,
+}
+
+/// The latest connection that presented a session token.
+struct Session {
+    client_id: ClientId,
+    disconnected_at: Option<Instant>,
+}
+
+/// Attempts to reconnect to the host after the connection was lost.
+#[derive(Clone, Copy)]
+struct Reconnection {
+    /// Number of the next attempt, starting at 1.
+    attempt: u32,
+    next_attempt_at: Instant,
+}
+
+impl Reconnection {
+    const MAX_DELAY: Duration = Duration::from_secs(30);
+    /// A host that doesn't answer within this time counts as a failed attempt.
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Gives a host that is restarting a moment before the first attempt.
+    fn first() -> Self {
+        Reconnection {
+            attempt: 1,
+            next_attempt_at: Instant::now() + Duration::from_secs(1),
+        }
+    }
+
+    /// The delay doubles with every failed attempt, up to `MAX_DELAY`.
+    fn next(self) -> Self {
+        let delay = Duration::from_secs(1 << self.attempt.min(5)).min(Self::MAX_DELAY);
+
+        Reconnection {
+            attempt: self.attempt + 1,
+            next_attempt_at: Instant::now() + delay,
+        }
+    }
 }
 
 impl ClientApp {
-    pub async fn connect(addr: SocketAddr) -> Result<Self, UnrecoverableError> {
-        let client = Client::connect(addr).await?;
-        let tui = Tui::new()?;
+    /// Without a room, the client shares the host's timer.
+    /// `addr` differs from the host's address if the connection is relayed, e.g. through a proxy.
+    pub async fn connect(
+        addr: SocketAddr,
+        host_addr: SocketAddr,
+        room: Option<String>,
+        notification_texts: NotificationTexts,
+        tui_config: tui::Config,
+    ) -> Result<Self, UnrecoverableError> {
+        // fail before connecting if there is no terminal
+        tui_config.sound_backend.warm_up();
+        let tui = Tui::new(tui_config)?;
+        let session_token = rand::random();
+        let mut client = Client::connect(addr).await?;
+        greet(&mut client, session_token, &room).await?;
 
-        Ok(Self { tui, client })
+        Ok(Self {
+            tui,
+            client,
+            host_addr,
+            session_token,
+            room,
+            notification_texts,
+            last_frame: None,
+            stale_after: Self::DEFAULT_STALE_AFTER,
+            reconnection: None,
+            connecting: None,
+            protocol_violation_shown: false,
+            throttle: EventThrottle::new(Instant::now()),
+        })
     }
 
+    /// Used until the host sent its frame interval, see `NetworkProtocol::FrameInterval`.
+    /// Hosts that broadcast on every tick send a frame at least once a second.
+    const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(3);
+
     pub async fn run(&mut self) -> Result<(), UnrecoverableError> {
         self.tui.enable()?;
         let result = self.run_inner().await;
@@ -186,44 +1162,286 @@ impl ClientApp {
     }
 
     async fn run_inner(&mut self) -> Result<(), UnrecoverableError> {
-        let network_status = NetworkStatus::from(&*self);
+        let mut staleness_check = interval(Duration::from_secs(1));
 
         loop {
             select! {
-                event = self.tui.read_event() => {
-                    let event = event?;
+                action = self.tui.read_event() => {
+                    // only the host can disconnect clients
+                    let Action::Event(event) = action? else {
+                        continue;
+                    };
                     if let Event::Quit = event {
                         break;
                     }
-                    self.client.send(&NetworkProtocol::Event(event)).await?;
+                    for event in self.throttle.push(event, Instant::now()) {
+                        self.send_event(event).await?;
+                    }
                 }
-                event = self.client.event() => {
+                _ = tokio::time::sleep_until(self.throttle.due_at().unwrap_or_else(Instant::now)), if self.throttle.due_at().is_some() => {
+                    if let Some(event) = self.throttle.take_due(Instant::now()) {
+                        self.send_event(event).await?;
+                    }
+                }
+                event = self.client.event(), if self.reconnection.is_none() => {
                     match event {
                         Ok(ClientEvent::Message(msg)) => {
                             match msg {
-                                NetworkProtocol::Display(visuals) => self.tui.render(&visuals, &network_status)?,
-                                NetworkProtocol::Notify(activity) => self.tui.show_notification(&activity.to_string(), true),
-                                _ => (), // received wrong type of message, ignore for now
+                                NetworkProtocol::Display(visuals) => {
+                                    self.last_frame = Some((visuals, Instant::now()));
+                                    self.tui.render(&visuals, &NetworkStatus::from(&*self))?;
+                                }
+                                NetworkProtocol::Notify(activity) => self.tui.show_notification(&self.notification_texts.completion(activity), true),
+                                NetworkProtocol::NotifySkip(activity) => self.tui.show_notification(&skip_notification_text(activity), false),
+                                NetworkProtocol::NotifyAdjustment(adjustment) => self.tui.show_hint(&adjustment.text()),
+                                NetworkProtocol::Announce(text) => {
+                                    let text = protocol::sanitize_announcement(&text);
+                                    self.tui.show_notification(&(strings().announcement)(&text), false);
+                                }
+                                NetworkProtocol::FrameInterval(frame_interval) => self.stale_after = stale_after(frame_interval),
+                                _ => {
+                                    warn!("host sent a message only clients may send");
+                                    if !mem::replace(&mut self.protocol_violation_shown, true) {
+                                        self.tui.show_hint(strings().host_violated_protocol);
+                                    }
+                                }
                             }
                         }
-                        Ok(ClientEvent::Disconnect(None)) => {
-                            return Err(ClientError::ServerDisconnect.into());
-                        }
-                        Ok(ClientEvent::Disconnect(Some(err))) => {
-                            return Err(err.into());
+                        Ok(ClientEvent::Disconnect(err)) => {
+                            warn!(err = ?err.map(|err| err.to_string()), "lost the connection to the host");
+                            self.connection_lost()?;
                         }
                         Err(err) => {
-                            return Err(err.into());
+                            warn!(%err, "lost the connection to the host");
+                            self.connection_lost()?;
                         }
                     }
                 }
+                _ = tokio::time::sleep_until(self.reconnection.map_or_else(Instant::now, |r| r.next_attempt_at)), if self.reconnection.is_some() && self.connecting.is_none() => {
+                    self.connecting = Some(tokio::spawn(connect(self.client.remote_addr)));
+                }
+                connected = async {
+                    match &mut self.connecting {
+                        Some(connecting) => connecting.await,
+                        None => ForeverPending.await.forever(),
+                    }
+                } => {
+                    self.connecting = None;
+                    self.reconnected(connected.ok().flatten()).await?;
+                }
+                _ = staleness_check.tick() => {
+                    if let Some((visuals, _)) = self.last_frame {
+                        let network_status = NetworkStatus::from(&*self);
+                        if let NetworkStatus::Client { stale_for: Some(_), .. } | NetworkStatus::Reconnecting { .. } = network_status {
+                            self.tui.render(&visuals, &network_status)?;
+                        }
+                    }
+                }
+            }
+        }
+        // quitting doesn't wait for an attempt to reconnect
+        if let Some(connecting) = self.connecting.take() {
+            connecting.abort();
+        }
+
+        Ok(())
+    }
+
+    async fn send_event(&mut self, event: Event) -> Result<(), UnrecoverableError> {
+        let message = match event {
+            Event::RequestSync => NetworkProtocol::RequestSync,
+            event => NetworkProtocol::Event(event),
+        };
+        // events can't be delivered while reconnecting, the host's timer is what counts
+        if self.reconnection.is_none() && self.client.send(&message).await.is_err() {
+            self.connection_lost()?;
+        }
+
+        Ok(())
+    }
+
+    fn connection_lost(&mut self) -> Result<(), UnrecoverableError> {
+        self.reconnection = Some(Reconnection::first());
+        self.render_last_frame()
+    }
+
+    /// Takes the result of an attempt to reconnect, see `connect`, and schedules the next one if it failed.
+    async fn reconnected(
+        &mut self,
+        client: Option<Client<NetworkProtocol>>,
+    ) -> Result<(), UnrecoverableError> {
+        let Some(reconnection) = self.reconnection else {
+            return Ok(());
+        };
+
+        match client {
+            Some(client) => {
+                self.client = client;
+                self.reconnection = None;
+                if greet(&mut self.client, self.session_token, &self.room)
+                    .await
+                    .is_err()
+                {
+                    return self.connection_lost();
+                }
             }
+            None => self.reconnection = Some(reconnection.next()),
+        }
+
+        self.render_last_frame()
+    }
+
+    fn render_last_frame(&mut self) -> Result<(), UnrecoverableError> {
+        if let Some((visuals, _)) = self.last_frame {
+            self.tui.render(&visuals, &NetworkStatus::from(&*self))?;
         }
 
         Ok(())
     }
 }
 
+/// One attempt to reconnect to the host, `None` if it failed or timed out, see `Reconnection::CONNECT_TIMEOUT`.
+async fn connect(addr: SocketAddr) -> Option<Client<NetworkProtocol>> {
+    match tokio::time::timeout(Reconnection::CONNECT_TIMEOUT, Client::connect(addr)).await {
+        Ok(Ok(client)) => Some(client),
+        Ok(Err(err)) => {
+            debug!(%err, "failed to reconnect");
+            None
+        }
+        Err(_) => {
+            debug!("timed out reconnecting");
+            None
+        }
+    }
+}
+
+/// The first messages of every connection to the host. The host forgets the room of a lost connection, so it is joined again.
+async fn greet(
+    client: &mut Client<NetworkProtocol>,
+    session_token: u64,
+    room: &Option<String>,
+) -> Result<(), ClientError> {
+    client.send(&NetworkProtocol::Resume(session_token)).await?;
+    if let Some(name) = room {
+        client
+            .send(&NetworkProtocol::JoinRoom(name.clone()))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Custom texts of the notifications shown when an activity starts because the previous one was completed.
+/// `None` stands for the default text.
+#[derive(Clone, Default)]
+pub struct NotificationTexts {
+    pub focus: Option<String>,
+    pub short_break: Option<String>,
+    pub long_break: Option<String>,
+}
+
+impl NotificationTexts {
+    /// `activity` is the one that starts after the previous one was completed.
+    fn completion(&self, activity: Activity) -> String {
+        let custom_text = match activity {
+            Activity::Focus => &self.focus,
+            Activity::ShortBreak => &self.short_break,
+            Activity::LongBreak => &self.long_break,
+        };
+
+        match (custom_text, activity) {
+            (Some(text), _) => text.clone(),
+            // the long break marks the end of a cycle
+            (None, Activity::LongBreak) => strings().long_break_started.to_string(),
+            (None, _) => activity.to_string(),
+        }
+    }
+}
+
+/// The adjustment if `event` changed the remaining time from `before` to `after`.
+fn adjustment(
+    event: &Event,
+    before: SessionDuration,
+    after: SessionDuration,
+    state: &State,
+    by: Option<ClientId>,
+) -> Option<Adjustment> {
+    let permanent = match event {
+        Event::ExtendActivity(_) | Event::ReduceActivity(_) => false,
+        Event::ExtendActivityPermanently(_) | Event::ReduceActivityPermanently(_) => true,
+        _ => return None,
+    };
+    if before == after {
+        return None;
+    }
+    let activity = state.current_activity();
+
+    Some(Adjustment {
+        extended: *after > *before,
+        amount: before.abs_diff(*after),
+        by: by.map(|client_id| client_id.to_string()),
+        new_default: permanent.then(|| (activity, state.settings().duration_of(activity))),
+    })
+}
+
+/// Whole minutes that have passed of the current activity.
+fn elapsed_minutes(state: &State) -> u64 {
+    (*state.current_activity_duration())
+        .saturating_sub(*state.time_remaining())
+        .as_secs()
+        / 60
+}
+
+/// Local time of day in minutes since midnight at which `focus_sessions` focus sessions will have been completed.
+/// Only projected within the next day, since the time of day would be ambiguous otherwise.
+fn done_by(state: &State, focus_sessions: u32) -> Option<u16> {
+    const MINUTES_PER_DAY: u64 = 24 * 60;
+
+    let remaining =
+        state.time_until_completed(focus_sessions, Duration::from_secs(MINUTES_PER_DAY * 60))?;
+    let minute_of_day = (u64::from(notification::local_minute_of_day())
+        + remaining.as_secs().div_ceil(60))
+        % MINUTES_PER_DAY;
+
+    Some(minute_of_day as u16)
+}
+
+/// See `App::needs_ticks`.
+fn needs_ticks(
+    config: &Config,
+    state: &State,
+    session_started: bool,
+    has_clients: bool,
+    has_hint: bool,
+) -> bool {
+    state.timer_is_active()
+        || has_clients
+        // expired hints disappear on the next render
+        || has_hint
+        || (config.show_elapsed && session_started)
+        // the time by which the goal is reached moves on while paused
+        || config.stop_after_focus_sessions.is_some()
+}
+
+/// A connection is considered stale after a few missed frames,
+/// but not over hiccups that are shorter than a second.
+fn stale_after(frame_interval: Duration) -> Duration {
+    frame_interval.saturating_mul(3).max(Duration::from_secs(1))
+}
+
+fn skip_notification_text(activity: Activity) -> String {
+    (strings().skipped_to)(&activity.to_string())
+}
+
+/// Why the app stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The user quit.
+    Quit,
+    /// All focus sessions of `Config::stop_after_focus_sessions` were completed.
+    Completed,
+}
+
 struct AppShouldQuit(bool);
 
 impl Deref for AppShouldQuit {
@@ -234,27 +1452,42 @@ impl Deref for AppShouldQuit {
     }
 }
 
-impl From<&App> for TimerVisuals {
-    fn from(app: &App) -> Self {
+impl From<&State> for TimerVisuals {
+    fn from(state: &State) -> Self {
+        let (session_counter_highlighted, session_counter_total) = state.session_counter();
+
         TimerVisuals {
-            time_remaining: app.pomodoro_state.time_remaining(),
-            timer_is_paused: !app.pomodoro_state.timer_is_active(),
-            activity: app.pomodoro_state.current_activity(),
-            progress_percentage: app.pomodoro_state.progress_percentage(),
-            completed_focus_sessions: app.pomodoro_state.completed_focus_sessions(),
+            time_remaining: state.time_remaining(),
+            timer_is_paused: !state.timer_is_active(),
+            activity: state.current_activity(),
+            progress_percentage: state.progress_percentage(),
+            preparation_remaining: state.preparation_remaining(),
+            completed_focus_sessions: state.completed_focus_sessions(),
+            focus_streak: state.focus_streak(),
+            session_counter_highlighted,
+            session_counter_total,
+            focus_sessions_until_long_break: state.focus_sessions_until_long_break(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NetworkStatus {
     Offline,
     Server {
-        connected_clients: Vec<String>,
-        listening_on: SocketAddr,
+        connected_clients: Vec<ClientId>,
+        listening_on: Vec<SocketAddr>,
+    },
+    /// The connection to the host was lost and is being reestablished.
+    Reconnecting {
+        attempt: u32,
+        next_in: Duration,
     },
     Client {
         connected_to: SocketAddr,
+        room: Option<String>,
+        /// Time since the last frame was received, if that is longer than expected.
+        stale_for: Option<Duration>,
     },
 }
 
@@ -262,18 +1495,43 @@ impl From<&App> for NetworkStatus {
     fn from(app: &App) -> Self {
         match &app.server {
             Some(server) => NetworkStatus::Server {
-                connected_clients: server.clients().iter().map(|c| format!("{c}")).collect(),
-                listening_on: server.local_addr,
+                connected_clients: sorted_clients(server),
+                listening_on: server.local_addrs(),
             },
             None => NetworkStatus::Offline,
         }
     }
 }
 
+/// Clients in a stable order, so that they can be referred to by their number in the client list.
+fn sorted_clients(server: &Servers) -> Vec<ClientId> {
+    let mut clients = server.clients();
+    clients.sort_by_key(|c| c.to_string());
+    clients
+}
+
 impl From<&ClientApp> for NetworkStatus {
     fn from(app: &ClientApp) -> Self {
+        if let Some(reconnection) = app.reconnection {
+            return NetworkStatus::Reconnecting {
+                attempt: reconnection.attempt,
+                next_in: reconnection
+                    .next_attempt_at
+                    .saturating_duration_since(Instant::now()),
+            };
+        }
+        // a paused host may legitimately be silent, see `Config::silent_while_paused`
+        let stale_for = match app.last_frame {
+            Some((visuals, received_at)) if !visuals.timer_is_paused => {
+                Some(received_at.elapsed()).filter(|&elapsed| elapsed >= app.stale_after)
+            }
+            _ => None,
+        };
+
         NetworkStatus::Client {
-            connected_to: app.client.remote_addr,
+            connected_to: app.host_addr,
+            room: app.room.clone(),
+            stale_for,
         }
     }
 }
@@ -287,6 +1545,8 @@ pub enum UnrecoverableError {
     NetworkClient(#[from] ClientError),
     #[error("network error: {0}")]
     NetworkServer(#[from] ServerError),
+    #[error("port {0} is a privileged port, use a port of {PRIVILEGED_PORTS_END} or above or run with elevated privileges ({1})")]
+    PrivilegedPort(u16, std::io::Error),
     #[error("failed to resolve hostname")]
     HostHasNoDnsRecords,
     #[error(
@@ -297,8 +1557,22 @@ pub enum UnrecoverableError {
         "failed to resolve hostname to an ipv6 address (but it can be resolved to an ipv4 address)"
     )]
     HostHasOnlyIpv4Records,
+    #[cfg(feature = "metrics")]
+    #[error("failed to serve metrics on {0}: {1}")]
+    MetricsBind(SocketAddr, std::io::Error),
+    #[cfg(feature = "web")]
+    #[error("failed to serve the web view on {0}: {1}")]
+    WebBind(SocketAddr, std::io::Error),
+    #[error("failed to open session log {}: {1}", .0.display())]
+    SessionLog(PathBuf, std::io::Error),
+    #[cfg(feature = "socks")]
+    #[error("proxy error: {0}")]
+    Proxy(#[from] crate::socks::ProxyError),
 }
 
+/// Ports below this one can only be bound with elevated privileges on most operating systems.
+const PRIVILEGED_PORTS_END: u16 = 1024;
+
 struct ForeverPending;
 
 impl ForeverPending {
@@ -321,3 +1595,239 @@ impl std::future::Future for ForeverPending {
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::Settings;
+
+    #[test]
+    fn paused_timers_without_clients_sleep() {
+        let config = Config::default();
+        let mut state = State::new(Settings::default());
+        assert!(!state.timer_is_active());
+        assert!(!needs_ticks(&config, &state, false, false, false));
+        assert!(!needs_ticks(&config, &state, true, false, false));
+
+        assert!(needs_ticks(&config, &state, false, true, false));
+        assert!(needs_ticks(&config, &state, false, false, true));
+        state.toggle_timer();
+        assert!(needs_ticks(&config, &state, false, false, false));
+    }
+
+    #[test]
+    fn paused_timers_tick_for_displays_that_change() {
+        let state = State::new(Settings::default());
+        let show_elapsed = Config {
+            show_elapsed: true,
+            ..Config::default()
+        };
+        assert!(!needs_ticks(&show_elapsed, &state, false, false, false));
+        assert!(needs_ticks(&show_elapsed, &state, true, false, false));
+
+        let goal = Config {
+            stop_after_focus_sessions: Some(4),
+            ..Config::default()
+        };
+        assert!(needs_ticks(&goal, &state, false, false, false));
+    }
+
+    #[test]
+    fn clients_expect_frames_as_often_as_the_host_sends_them() {
+        let every_tick = Config::default();
+        assert_eq!(every_tick.frame_interval(), Duration::from_millis(100));
+        assert_eq!(
+            stale_after(every_tick.frame_interval()),
+            Duration::from_secs(1)
+        );
+
+        let long_ticks = Config {
+            tick_interval: Duration::from_secs(1),
+            ..Config::default()
+        };
+        assert_eq!(long_ticks.frame_interval(), Duration::from_secs(1));
+        assert_eq!(
+            stale_after(long_ticks.frame_interval()),
+            Duration::from_secs(3)
+        );
+
+        let throttled = Config {
+            broadcast_interval: Some(Duration::from_secs(1)),
+            ..long_ticks
+        };
+        assert_eq!(throttled.frame_interval(), Duration::from_secs(2));
+        assert_eq!(
+            stale_after(throttled.frame_interval()),
+            Duration::from_secs(6)
+        );
+
+        assert_eq!(stale_after(Duration::MAX), Duration::MAX);
+    }
+
+    #[tokio::test]
+    async fn frames_are_only_built_for_clients() {
+        let mut server = Servers::host(&["127.0.0.1:0".parse().unwrap()])
+            .await
+            .expect("can bind to localhost");
+        let mut rooms = HashMap::from([(
+            "room".to_string(),
+            Room::new(Settings::default(), Instant::now()),
+        )]);
+        let visuals = TimerVisuals::from(&State::new(Settings::default()));
+        let mut last_broadcast_visuals = None;
+
+        let broadcast = broadcast_frames(
+            &mut server,
+            &HashMap::new(),
+            &mut rooms,
+            visuals,
+            &mut last_broadcast_visuals,
+            false,
+        )
+        .await
+        .expect("nothing to send");
+        assert!(!broadcast);
+        assert!(last_broadcast_visuals.is_none());
+        assert!(rooms["room"].last_broadcast_visuals.is_none());
+
+        let mut client = Client::<NetworkProtocol>::connect(server.local_addrs()[0])
+            .await
+            .expect("can connect to the server");
+        assert!(matches!(
+            server.event().await,
+            Ok(ServerEvent::NewConnection(_))
+        ));
+        let broadcast = broadcast_frames(
+            &mut server,
+            &HashMap::new(),
+            &mut rooms,
+            visuals,
+            &mut last_broadcast_visuals,
+            false,
+        )
+        .await
+        .expect("the client is connected");
+        assert!(broadcast);
+        assert!(last_broadcast_visuals == Some(visuals));
+        assert!(rooms["room"].last_broadcast_visuals.is_some());
+        assert!(matches!(
+            client.event().await,
+            Ok(ClientEvent::Message(NetworkProtocol::Display(_)))
+        ));
+    }
+
+    /// An app whose tui never touches the terminal, hosting on a free port of localhost.
+    async fn headless_host(settings: Settings, config: Config) -> (App, SocketAddr) {
+        let tui = Tui::headless(config.tui.clone());
+        let mut app = App::with_tui(State::new(settings), config, tui).expect("nothing to open");
+        app.start_server(&["127.0.0.1:0".parse().unwrap()])
+            .await
+            .expect("can bind to localhost");
+        let address = app.server.as_ref().unwrap().local_addrs()[0];
+        (app, address)
+    }
+
+    fn quiet_config() -> Config {
+        Config {
+            tick_interval: Duration::from_secs(1),
+            tui: tui::Config {
+                sound_backend: notification::SoundBackend::None,
+                ..tui::Config::default()
+            },
+            ..Config::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn clients_follow_a_full_cycle_in_virtual_time() {
+        let settings = Settings {
+            start_automatically: true,
+            ..Settings::default()
+        };
+        let (mut app, address) = headless_host(settings, quiet_config()).await;
+        let started_at = Instant::now();
+
+        let client = async {
+            let mut client = Client::<NetworkProtocol>::connect(address)
+                .await
+                .expect("can connect to the host");
+            let mut notifications = Vec::new();
+            let mut frames = 0;
+            while notifications.len() < 8 {
+                match client.event().await {
+                    Ok(ClientEvent::Message(NetworkProtocol::Notify(activity))) => {
+                        notifications.push((activity, started_at.elapsed().as_secs() / 60));
+                    }
+                    Ok(ClientEvent::Message(NetworkProtocol::Display(_))) => frames += 1,
+                    Ok(ClientEvent::Message(_)) => {}
+                    Ok(ClientEvent::Disconnect(err)) => panic!("disconnected: {err:?}"),
+                    Err(err) => panic!("client error: {err}"),
+                }
+            }
+            (notifications, frames)
+        };
+        let (notifications, frames) = select! {
+            outcome = app.run_inner() => panic!("the app stopped: {:?}", outcome.err()),
+            received = client => received,
+        };
+
+        assert_eq!(
+            notifications,
+            [
+                (Activity::ShortBreak, 25),
+                (Activity::Focus, 30),
+                (Activity::ShortBreak, 55),
+                (Activity::Focus, 60),
+                (Activity::ShortBreak, 85),
+                (Activity::Focus, 90),
+                (Activity::LongBreak, 115),
+                (Activity::Focus, 135),
+            ]
+        );
+        // a frame for every tick of the running timer
+        assert!(frames >= 135 * 60, "only {frames} frames");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flooding_clients_are_throttled_but_stay_connected() {
+        let config = quiet_config();
+        let events_per_second = config.client_events_per_second;
+        let (mut app, address) = headless_host(Settings::default(), config).await;
+        let remaining_before = *app.pomodoro_state.time_remaining();
+
+        let client = async {
+            let mut client = Client::<NetworkProtocol>::connect(address)
+                .await
+                .expect("can connect to the host");
+            for _ in 0..5 {
+                for _ in 0..2 * events_per_second {
+                    client
+                        .send(&NetworkProtocol::Event(Event::ExtendActivity(
+                            Duration::from_secs(1),
+                        )))
+                        .await
+                        .expect("the host is still connected");
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        };
+        select! {
+            outcome = app.run_inner() => panic!("the app stopped: {:?}", outcome.err()),
+            () = client => {}
+        }
+
+        assert_eq!(app.server.as_ref().unwrap().clients().len(), 1);
+        assert!(app
+            .protocol_violations
+            .values()
+            .all(|&violations| violations == 0));
+        // the host reads the messages as it gets to them, a burst may spill over into the next second
+        let extended = (*app.pomodoro_state.time_remaining() - remaining_before).as_secs();
+        let sent = 5 * 2 * u64::from(events_per_second);
+        assert!(
+            extended >= u64::from(events_per_second),
+            "extended by {extended}s"
+        );
+        assert!(extended < sent / 2, "extended by {extended}s");
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use tokio::time::{Duration, Instant};
+
+/// Limits how many messages every client may send per second, so that a buggy or malicious client
+/// can't flood the timer and every other client with changes. Clients are told apart by `K`.
+pub struct RateLimiter<K> {
+    events_per_second: u32,
+    windows: HashMap<K, Window>,
+}
+
+struct Window {
+    started_at: Instant,
+    events: u32,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum Admission {
+    Allowed,
+    /// The first event that was dropped in the current second.
+    StartedDropping,
+    Dropped,
+}
+
+impl<K: Copy + Eq + Hash> RateLimiter<K> {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    pub fn new(events_per_second: u32) -> Self {
+        Self {
+            events_per_second,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Counts a message of `client` received at `now`.
+    pub fn admit(&mut self, client: K, now: Instant) -> Admission {
+        let window = self.windows.entry(client).or_insert(Window {
+            started_at: now,
+            events: 0,
+        });
+        if now.duration_since(window.started_at) >= Self::WINDOW {
+            *window = Window {
+                started_at: now,
+                events: 0,
+            };
+        }
+        window.events = window.events.saturating_add(1);
+
+        match window.events {
+            events if events <= self.events_per_second => Admission::Allowed,
+            events if events == self.events_per_second + 1 => Admission::StartedDropping,
+            _ => Admission::Dropped,
+        }
+    }
+
+    /// Forgets clients whose window ended, for clients that can't be told to be disconnected.
+    #[cfg(any(feature = "web", test))]
+    pub fn forget_idle(&mut self, now: Instant) {
+        self.windows
+            .retain(|_, window| now.duration_since(window.started_at) < Self::WINDOW);
+    }
+
+    /// Forgets clients that are no longer connected.
+    pub fn retain(&mut self, connected_clients: &[K]) {
+        self.windows
+            .retain(|client, _| connected_clients.contains(client));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admissions(
+        limiter: &mut RateLimiter<u32>,
+        client: u32,
+        count: usize,
+        now: Instant,
+    ) -> Vec<Admission> {
+        (0..count).map(|_| limiter.admit(client, now)).collect()
+    }
+
+    #[test]
+    fn drops_a_flood_until_the_window_ends() {
+        let mut limiter = RateLimiter::new(3);
+        let start = Instant::now();
+
+        assert_eq!(
+            admissions(&mut limiter, 1, 6, start),
+            [
+                Admission::Allowed,
+                Admission::Allowed,
+                Admission::Allowed,
+                Admission::StartedDropping,
+                Admission::Dropped,
+                Admission::Dropped,
+            ]
+        );
+        let almost_a_second_later = start + RateLimiter::<u32>::WINDOW - Duration::from_millis(1);
+        assert_eq!(limiter.admit(1, almost_a_second_later), Admission::Dropped);
+
+        let next_window = start + RateLimiter::<u32>::WINDOW;
+        assert_eq!(
+            admissions(&mut limiter, 1, 4, next_window),
+            [
+                Admission::Allowed,
+                Admission::Allowed,
+                Admission::Allowed,
+                Admission::StartedDropping,
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_every_client_separately() {
+        let mut limiter = RateLimiter::new(1);
+        let now = Instant::now();
+
+        assert_eq!(limiter.admit(1, now), Admission::Allowed);
+        assert_eq!(limiter.admit(1, now), Admission::StartedDropping);
+        assert_eq!(limiter.admit(2, now), Admission::Allowed);
+    }
+
+    #[test]
+    fn forgets_idle_clients() {
+        let mut limiter = RateLimiter::new(1);
+        let now = Instant::now();
+
+        assert_eq!(limiter.admit(1, now), Admission::Allowed);
+        limiter.forget_idle(now);
+        assert_eq!(limiter.windows.len(), 1);
+        limiter.forget_idle(now + RateLimiter::<u32>::WINDOW);
+        assert!(limiter.windows.is_empty());
+    }
+
+    #[test]
+    fn forgets_disconnected_clients() {
+        let mut limiter = RateLimiter::new(1);
+        let now = Instant::now();
+
+        assert_eq!(limiter.admit(1, now), Admission::Allowed);
+        limiter.retain(&[2]);
+        assert_eq!(limiter.admit(1, now), Admission::Allowed);
+    }
+}
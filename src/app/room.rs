@@ -0,0 +1,165 @@
+use super::apply_timer_event;
+use crate::pomodoro::{Activity, Settings, State};
+use crate::protocol::{Event, TimerVisuals};
+use tokio::time::Instant;
+
+/// A timer that a group of clients shares independently of the host's own timer.
+pub struct Room {
+    pub state: State,
+    /// When progress was last added to the timer.
+    progressed_at: Instant,
+    pub last_broadcast_visuals: Option<TimerVisuals>,
+}
+
+impl Room {
+    pub fn new(settings: Settings, now: Instant) -> Self {
+        Self {
+            state: State::new(settings),
+            progressed_at: now,
+            last_broadcast_visuals: None,
+        }
+    }
+
+    /// Adds the time since the last tick to the timer if it runs.
+    /// Returns the new activity if the previous one was completed.
+    pub fn tick(&mut self, now: Instant) -> Option<Activity> {
+        let elapsed = now - self.progressed_at;
+        self.progressed_at = now;
+
+        if !self.state.timer_is_active() {
+            return None;
+        }
+
+        let activity_before = self.state.current_activity();
+        self.state.increase_progress(elapsed);
+        let activity_after = self.state.current_activity();
+
+        (activity_before != activity_after).then_some(activity_after)
+    }
+
+    /// Returns the new activity if it was skipped to.
+    pub fn handle_event(&mut self, event: &Event, now: Instant) -> Option<Activity> {
+        let timer_was_stopped = !self.state.timer_is_active();
+        let skipped_to = apply_timer_event(&mut self.state, event);
+
+        if timer_was_stopped && self.state.timer_is_active() {
+            self.progressed_at = now;
+        }
+
+        skipped_to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::NotificationTexts;
+    use crate::pomodoro::SessionDuration;
+    use crate::strings::strings;
+    use std::time::Duration;
+    use tokio::time;
+
+    fn minutes(minutes: u64) -> SessionDuration {
+        SessionDuration(Duration::from_secs(minutes * 60))
+    }
+
+    fn settings(start_automatically: bool) -> Settings {
+        Settings::new(
+            minutes(25),
+            minutes(5),
+            minutes(20),
+            Some(4),
+            start_automatically,
+            Settings::DEFAULT_MAX_ACTIVITY_DURATION,
+            SessionDuration::default(),
+        )
+    }
+
+    /// Ticks the room once a second for the given number of minutes,
+    /// returns every completed activity with the minute in which it was completed.
+    async fn run_for(room: &mut Room, minutes: u64) -> Vec<(Activity, u64)> {
+        let started_at = Instant::now();
+        let mut completed = Vec::new();
+        for _ in 0..minutes * 60 {
+            time::advance(Duration::from_secs(1)).await;
+            if let Some(activity) = room.tick(Instant::now()) {
+                completed.push((activity, (Instant::now() - started_at).as_secs() / 60));
+            }
+        }
+        completed
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_through_a_full_cycle() {
+        let mut room = Room::new(settings(true), Instant::now());
+
+        let completed = run_for(&mut room, 2 * 135).await;
+
+        let first_cycle = [
+            (Activity::ShortBreak, 25),
+            (Activity::Focus, 30),
+            (Activity::ShortBreak, 55),
+            (Activity::Focus, 60),
+            (Activity::ShortBreak, 85),
+            (Activity::Focus, 90),
+            (Activity::LongBreak, 115),
+            (Activity::Focus, 135),
+        ];
+        let second_cycle = first_cycle.map(|(activity, minute)| (activity, minute + 135));
+        assert_eq!(completed, [first_cycle, second_cycle].concat());
+        assert_eq!(room.state.completed_focus_sessions(), 8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_time_does_not_count() {
+        let mut room = Room::new(settings(false), Instant::now());
+        assert!(run_for(&mut room, 60).await.is_empty());
+
+        room.handle_event(&Event::ToggleTimer, Instant::now());
+        assert!(run_for(&mut room, 10).await.is_empty());
+        room.handle_event(&Event::ToggleTimer, Instant::now());
+        time::advance(Duration::from_secs(60 * 60)).await;
+        assert_eq!(room.tick(Instant::now()), None);
+        room.handle_event(&Event::ToggleTimer, Instant::now());
+
+        assert_eq!(run_for(&mut room, 15).await, [(Activity::ShortBreak, 15)]);
+        // without starting automatically, the timer waits for the next activity to be started
+        assert!(!room.state.timer_is_active());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skipping_notifies_the_new_activity() {
+        let mut room = Room::new(settings(true), Instant::now());
+
+        assert_eq!(
+            room.handle_event(&Event::SkipActivity, Instant::now()),
+            Some(Activity::ShortBreak)
+        );
+        assert_eq!(
+            TimerVisuals::from(&room.state).activity,
+            Activity::ShortBreak
+        );
+        assert_eq!(run_for(&mut room, 5).await, [(Activity::Focus, 5)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn announces_the_long_break_once_per_cycle() {
+        let settings = Settings {
+            sessions_before_long_break: Some(6),
+            ..settings(true)
+        };
+        let mut room = Room::new(settings, Instant::now());
+
+        // six focus sessions with five short breaks in between
+        let cycle = 6 * 25 + 5 * 5 + 20;
+        let completed = run_for(&mut room, cycle).await;
+
+        let texts = NotificationTexts::default();
+        let long_breaks: Vec<_> = completed
+            .iter()
+            .filter(|(activity, _)| texts.completion(*activity) == strings().long_break_started)
+            .collect();
+        assert_eq!(long_breaks, [&(Activity::LongBreak, 6 * 25 + 5 * 5)]);
+        assert_eq!(completed.last(), Some(&(Activity::Focus, cycle)));
+    }
+}
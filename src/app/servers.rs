@@ -0,0 +1,92 @@
+use crate::protocol::NetworkProtocol;
+use futures::future::select_all;
+use std::io;
+use std::net::SocketAddr;
+use zwiesel::{ClientId, Server, ServerError, ServerEvent};
+
+/// One or more servers that act as one, e.g. an IPv4 and an IPv6 listener on the same port.
+/// Client ids are random, so they don't collide between the servers.
+pub struct Servers {
+    servers: Vec<Server<NetworkProtocol>>,
+    /// Includes IPv4 addresses that are served by an IPv6 listener.
+    local_addrs: Vec<SocketAddr>,
+}
+
+impl Servers {
+    /// Binds one server per socket, `sockets` must not be empty.
+    pub async fn host(sockets: &[SocketAddr]) -> Result<Self, ServerError> {
+        let mut servers: Vec<Server<NetworkProtocol>> = Vec::with_capacity(sockets.len());
+        let mut local_addrs = Vec::with_capacity(sockets.len());
+        for &socket in sockets {
+            match Server::host(socket).await {
+                Ok(server) => {
+                    local_addrs.push(server.local_addr);
+                    servers.push(server);
+                }
+                // an IPv6 listener may already accept IPv4 connections, depending on the operating system
+                Err(ServerError::Bind(_, err))
+                    if err.kind() == io::ErrorKind::AddrInUse
+                        && socket.is_ipv4()
+                        && servers.iter().any(|s| s.local_addr.is_ipv6()) =>
+                {
+                    local_addrs.push(socket);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(Self {
+            servers,
+            local_addrs,
+        })
+    }
+
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.local_addrs.clone()
+    }
+
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.servers.iter().flat_map(Server::clients).collect()
+    }
+
+    pub fn has_clients(&self) -> bool {
+        self.servers.iter().any(|s| !s.clients().is_empty())
+    }
+
+    /// Errors indicate that the respective client is no longer connected.
+    pub async fn send(
+        &mut self,
+        client_id: ClientId,
+        message: &NetworkProtocol,
+    ) -> Result<(), ServerError> {
+        match self.server_of(client_id) {
+            Some(server) => server.send(client_id, message).await,
+            None => Err(ServerError::ClientDoesNotExist(client_id)),
+        }
+    }
+
+    pub fn disconnect(&mut self, client_id: ClientId) -> Result<(), ServerError> {
+        match self.server_of(client_id) {
+            Some(server) => server.disconnect(client_id),
+            None => Err(ServerError::ClientDoesNotExist(client_id)),
+        }
+    }
+
+    pub fn disconnect_all(&mut self) {
+        self.servers.iter_mut().for_each(Server::disconnect_all);
+    }
+
+    /// Waits for an inbound network event on any of the servers.
+    pub async fn event(&mut self) -> Result<ServerEvent<NetworkProtocol>, ServerError> {
+        let events = self.servers.iter_mut().map(|s| Box::pin(s.event()));
+        let (event, _, _) = select_all(events).await;
+
+        event
+    }
+
+    fn server_of(&mut self, client_id: ClientId) -> Option<&mut Server<NetworkProtocol>> {
+        self.servers
+            .iter_mut()
+            .find(|s| s.clients().contains(&client_id))
+    }
+}
@@ -0,0 +1,166 @@
+use crate::protocol::Event;
+use tokio::time::{Duration, Instant};
+
+/// Coalesces the events that dragging or scrolling with the mouse sends many times a second,
+/// so that a client stays well below the host's rate limit, see `app::Config::client_events_per_second`.
+/// Other events are sent right away, after the coalesced event that came before them.
+pub struct EventThrottle {
+    pending: Option<Event>,
+    next_send_at: Instant,
+}
+
+impl EventThrottle {
+    /// At most 10 coalesced events per second, a third of the host's default limit.
+    pub const INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new(now: Instant) -> Self {
+        Self {
+            pending: None,
+            next_send_at: now,
+        }
+    }
+
+    /// Returns the events to send now, in order.
+    pub fn push(&mut self, event: Event, now: Instant) -> Vec<Event> {
+        if !is_coalesced(&event) {
+            return self.pending.take().into_iter().chain([event]).collect();
+        }
+
+        let mut ready = Vec::new();
+        self.pending = match self.pending.take() {
+            None => Some(event),
+            Some(pending) => match merge(pending, event) {
+                Ok(merged) => Some(merged),
+                // a different kind of change, the pending one can't wait for it
+                Err((pending, event)) => {
+                    ready.push(pending);
+                    self.next_send_at = now + Self::INTERVAL;
+                    Some(event)
+                }
+            },
+        };
+        ready.extend(self.take_due(now));
+
+        ready
+    }
+
+    /// When the pending event is due, `None` if there is none.
+    pub fn due_at(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|_| self.next_send_at)
+    }
+
+    /// The pending event, if it is due at `now`.
+    pub fn take_due(&mut self, now: Instant) -> Option<Event> {
+        if now < self.next_send_at {
+            return None;
+        }
+        let event = self.pending.take()?;
+        self.next_send_at = now + Self::INTERVAL;
+
+        Some(event)
+    }
+}
+
+fn is_coalesced(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::SetProgress(_) | Event::ExtendActivity(_) | Event::ReduceActivity(_)
+    )
+}
+
+/// Only the last position counts, adjustments of the same direction add up.
+fn merge(pending: Event, event: Event) -> Result<Event, (Event, Event)> {
+    match (pending, event) {
+        (Event::SetProgress(_), Event::SetProgress(fraction)) => Ok(Event::SetProgress(fraction)),
+        (Event::ExtendActivity(a), Event::ExtendActivity(b)) => {
+            Ok(Event::ExtendActivity(a.saturating_add(b)))
+        }
+        (Event::ReduceActivity(a), Event::ReduceActivity(b)) => {
+            Ok(Event::ReduceActivity(a.saturating_add(b)))
+        }
+        events => Err(events),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn a_drag_is_sent_at_most_once_per_interval() {
+        let start = Instant::now();
+        let mut throttle = EventThrottle::new(start);
+
+        assert_eq!(
+            throttle.push(Event::SetProgress(0.1), start),
+            [Event::SetProgress(0.1)]
+        );
+        let mut now = start;
+        for fraction in [0.2, 0.3, 0.4] {
+            now += Duration::from_millis(10);
+            assert!(throttle.push(Event::SetProgress(fraction), now).is_empty());
+        }
+        assert_eq!(throttle.due_at(), Some(start + EventThrottle::INTERVAL));
+        assert_eq!(throttle.take_due(now), None);
+
+        let due_at = start + EventThrottle::INTERVAL;
+        assert_eq!(throttle.take_due(due_at), Some(Event::SetProgress(0.4)));
+        assert_eq!(throttle.due_at(), None);
+        assert_eq!(throttle.take_due(due_at + EventThrottle::INTERVAL), None);
+    }
+
+    #[test]
+    fn scrolling_adds_up() {
+        let start = Instant::now();
+        let mut throttle = EventThrottle::new(start);
+
+        assert_eq!(
+            throttle.push(Event::ExtendActivity(STEP), start),
+            [Event::ExtendActivity(STEP)]
+        );
+        assert!(throttle.push(Event::ExtendActivity(STEP), start).is_empty());
+        assert!(throttle.push(Event::ExtendActivity(STEP), start).is_empty());
+        assert_eq!(
+            throttle.take_due(start + EventThrottle::INTERVAL),
+            Some(Event::ExtendActivity(2 * STEP))
+        );
+    }
+
+    #[test]
+    fn changing_direction_sends_the_pending_adjustment() {
+        let start = Instant::now();
+        let mut throttle = EventThrottle::new(start);
+        throttle.push(Event::ExtendActivity(STEP), start);
+        throttle.push(Event::ExtendActivity(STEP), start);
+
+        assert_eq!(
+            throttle.push(Event::ReduceActivity(STEP), start),
+            [Event::ExtendActivity(STEP)]
+        );
+        assert_eq!(throttle.due_at(), Some(start + EventThrottle::INTERVAL));
+        assert_eq!(
+            throttle.take_due(start + EventThrottle::INTERVAL),
+            Some(Event::ReduceActivity(STEP))
+        );
+    }
+
+    #[test]
+    fn other_events_are_sent_right_away_and_in_order() {
+        let start = Instant::now();
+        let mut throttle = EventThrottle::new(start);
+        assert_eq!(
+            throttle.push(Event::ToggleTimer, start),
+            [Event::ToggleTimer]
+        );
+
+        throttle.push(Event::SetProgress(0.1), start);
+        throttle.push(Event::SetProgress(0.2), start);
+        assert_eq!(
+            throttle.push(Event::ToggleTimer, start),
+            [Event::SetProgress(0.2), Event::ToggleTimer]
+        );
+        assert_eq!(throttle.due_at(), None);
+    }
+}
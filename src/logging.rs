@@ -0,0 +1,33 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::Level;
+
+/// Sets up diagnostic logging. `-v` logs informational messages, `-vv` debug messages and `-vvv` everything.
+/// Without `-v`, only warnings and errors are logged, and only if there is a log file.
+/// The tui owns the terminal, so while it runs logs are only written to `log_file`, otherwise they go to stderr.
+pub fn init(verbosity: u8, log_file: Option<&Path>, uses_tui: bool) -> io::Result<()> {
+    let level = match verbosity {
+        0 if log_file.is_none() => return Ok(()),
+        0 => Level::WARN,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    let builder = tracing_subscriber::fmt().with_max_level(level);
+
+    match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .init();
+        }
+        None if uses_tui => {}
+        None => builder.with_writer(io::stderr).init(),
+    }
+
+    Ok(())
+}
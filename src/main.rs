@@ -1,64 +1,433 @@
-use crate::app::{App, ClientApp, UnrecoverableError};
+use crate::app::{App, ClientApp, Config, NotificationTexts, RunOutcome, UnrecoverableError};
 use crate::args::{Args, IpVersion, Parser};
+use crate::notification::{NotifyBackend, SoundBackend};
 use rand::{seq::IteratorRandom, thread_rng};
+use std::fs;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
 use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 mod app;
 mod args;
+mod logging;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod notification;
 mod pomodoro;
 mod protocol;
+mod session_log;
+#[cfg(feature = "socks")]
+mod socks;
+mod stats;
+mod strings;
 mod tui;
+#[cfg(feature = "web")]
+mod web;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> ExitCode {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.version_verbose {
+        print_version_verbose();
+        return ExitCode::SUCCESS;
+    }
+    let command = args.command_or_exit();
+    strings::set_lang(args.lang);
+    let uses_tui = matches!(
+        command,
+        args::Command::Offline { plan: false, .. }
+            | args::Command::Connect { .. }
+            | args::Command::Host { .. }
+    );
+    if let Err(err) = logging::init(args.verbose, args.debug_log.as_deref(), uses_tui) {
+        eprintln!("failed to open the debug log: {err}");
+        return ExitCode::FAILURE;
+    }
+    let (counter_glyphs, warnings) = tui::CounterGlyphs::default().replaced(
+        args.counter_filled,
+        args.counter_empty,
+        args.counter_separator,
+    );
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+    let notify_backend = if args.no_desktop_notifications {
+        NotifyBackend::None
+    } else {
+        args.notify_backend
+    };
+    let sound_backend = if args.no_sound {
+        SoundBackend::None
+    } else {
+        args.sound_backend
+    };
+    let tui_config = tui::Config {
+        settings_percentage: args.split,
+        show_legend: !args.no_legend,
+        quiet_hours: args.quiet_hours,
+        notify_backend,
+        sound_backend,
+        mouse: !args.no_mouse,
+        alternate_screen: !args.no_alt_screen && !args.accessible,
+        terminal_progress: args.terminal_progress,
+        accessible: args.accessible,
+        snapshot_dir: args.snapshot_dir,
+        theme: tui::Theme {
+            counter: counter_glyphs,
+            clock_edge: args.clock_edge.then_some(::tui::style::Modifier::DIM),
+            ..match args.accessible {
+                true => tui::Theme::mono(),
+                false => tui::Theme::for_color_mode(args.colors),
+            }
+        },
+        ..Default::default()
+    };
+    let notification_texts = NotificationTexts {
+        focus: args.focus_text,
+        short_break: args.short_break_text,
+        long_break: args.long_break_text,
+    };
 
-    let result = match args.command {
-        args::Command::Offline { focus, short, long } => run_offline(focus, short, long).await,
+    let result = match command {
+        args::Command::Offline { timer, plan } => {
+            let state = timer.state().unwrap_or_else(|err| err.exit());
+            let config = Config {
+                tick_interval: timer.tick_interval(),
+                smooth_animation: timer.smooth,
+                idle_animation: timer.idle_animation,
+                show_elapsed: timer.show_elapsed,
+                session_log: timer.log_file,
+                record_stats: !timer.no_stats,
+                show_focus_sessions_today: timer.pomodoro_count,
+                focus_lock: timer.focus_lock,
+                minute_chime: timer.minute_chime,
+                renotify_after: timer.renotify_after.map(|duration| *duration),
+                check_in: timer
+                    .check_in_after
+                    .map(|after| (*after, *timer.check_in_grace)),
+                stop_after_focus_sessions: timer.sessions,
+                notification_texts,
+                tui: tui_config,
+                ..Default::default()
+            };
+
+            if plan {
+                print_plan(state);
+                Ok(ExitCode::SUCCESS)
+            } else {
+                run_offline(state, config)
+                    .await
+                    .map(|outcome| exit_code(outcome, timer.sessions))
+            }
+        }
         args::Command::Connect {
             server_address,
             ip_version,
-        } => run_client(server_address.resolved(), ip_version).await,
+            room,
+            #[cfg(feature = "socks")]
+            proxy,
+        } => run_client(
+            server_address.resolved(),
+            ip_version,
+            room.or_else(|| server_address.room().map(str::to_string)),
+            notification_texts,
+            tui_config,
+            #[cfg(feature = "socks")]
+            proxy,
+        )
+        .await
+        .map(|()| ExitCode::SUCCESS),
         args::Command::Host {
             port,
+            timer,
             ip_version,
-            focus,
-            short,
-            long,
-        } => run_server(port, ip_version, focus, short, long).await,
+            silent_while_paused,
+            broadcast_interval,
+            max_client_events,
+            detach_ui,
+            no_quit_confirmation,
+            pause_when_empty,
+            #[cfg(feature = "metrics")]
+            metrics_port,
+            #[cfg(feature = "web")]
+            web_port,
+        } => {
+            let state = timer.state().unwrap_or_else(|err| err.exit());
+            let config = Config {
+                tick_interval: timer.tick_interval(),
+                smooth_animation: timer.smooth,
+                idle_animation: timer.idle_animation,
+                show_elapsed: timer.show_elapsed,
+                session_log: timer.log_file,
+                record_stats: !timer.no_stats,
+                show_focus_sessions_today: timer.pomodoro_count,
+                focus_lock: timer.focus_lock,
+                minute_chime: timer.minute_chime,
+                renotify_after: timer.renotify_after.map(|duration| *duration),
+                check_in: timer
+                    .check_in_after
+                    .map(|after| (*after, *timer.check_in_grace)),
+                stop_after_focus_sessions: timer.sessions,
+                silent_while_paused,
+                broadcast_interval: broadcast_interval.map(Duration::from_millis),
+                client_events_per_second: max_client_events,
+                detach_ui,
+                confirm_quit: !no_quit_confirmation,
+                pause_when_empty,
+                notification_texts,
+                tui: tui_config,
+            };
+
+            run_server(
+                port,
+                ip_version,
+                state,
+                config,
+                #[cfg(feature = "metrics")]
+                metrics_port,
+                #[cfg(feature = "web")]
+                web_port,
+            )
+            .await
+            .map(|outcome| exit_code(outcome, timer.sessions))
+        }
+        args::Command::Defaults => {
+            print_defaults();
+            Ok(ExitCode::SUCCESS)
+        }
+        args::Command::Stats => return print_stats(),
+        args::Command::Export { format } => return export_stats(format),
+        args::Command::Replay { log_file } => return replay(&log_file),
+        args::Command::TestNotify => return test_notify(notify_backend, sound_backend),
+        args::Command::BenchNotify { count } => return bench_notify(notify_backend, count),
     };
 
-    if let Err(err) = result {
-        eprintln!("{err}");
-        ExitCode::FAILURE
+    match result {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// See `args::EXIT_CODES`. Without a number of sessions to complete, quitting is the normal way to stop.
+fn exit_code(outcome: RunOutcome, sessions: Option<u32>) -> ExitCode {
+    match (outcome, sessions) {
+        (RunOutcome::Quit, Some(_)) => ExitCode::from(3),
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+/// The build info is embedded by the build script.
+fn print_version_verbose() {
+    let features: Vec<&str> = [
+        ("metrics", cfg!(feature = "metrics")),
+        ("web", cfg!(feature = "web")),
+        ("socks", cfg!(feature = "socks")),
+    ]
+    .into_iter()
+    .filter_map(|(feature, enabled)| enabled.then_some(feature))
+    .collect();
+
+    println!("corrodoro {}", env!("CARGO_PKG_VERSION"));
+    println!("commit: {}", env!("CORRODORO_GIT_COMMIT"));
+    println!("build date: {}", env!("CORRODORO_BUILD_DATE"));
+    println!("target: {}", env!("CORRODORO_TARGET"));
+    println!("profile: {}", env!("CORRODORO_PROFILE"));
+    if features.is_empty() {
+        println!("features: none");
     } else {
-        ExitCode::SUCCESS
+        println!("features: {}", features.join(", "));
+    }
+}
+
+/// Durations are in whole seconds, the keys are stable.
+fn print_defaults() {
+    let settings = pomodoro::Settings::default();
+
+    println!(
+        "{{\"focus_seconds\":{},\"short_break_seconds\":{},\"long_break_seconds\":{},\"sessions_before_long_break\":{}}}",
+        settings.focus_duration.as_secs(),
+        settings.short_break_duration.as_secs(),
+        settings.long_break_duration.as_secs(),
+        settings
+            .sessions_before_long_break
+            .map_or("null".to_string(), |n| n.to_string())
+    );
+}
+
+fn print_stats() -> ExitCode {
+    match stats::LifetimeStats::load() {
+        Ok(stats) => {
+            println!("{stats}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn export_stats(format: args::ExportFormat) -> ExitCode {
+    match stats::LifetimeStats::load() {
+        Ok(stats) => {
+            match format {
+                args::ExportFormat::Json => println!("{}", stats.to_json()),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints one line per logged activity with the local time at which it ran.
+/// Lines that can't be parsed are skipped with a warning, so that a log cut off by a crash can still be read.
+fn replay(log_file: &Path) -> ExitCode {
+    let content = match fs::read_to_string(log_file) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("failed to read {}: {err}", log_file.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let time_of_day = |timestamp| {
+        let minute_of_day = notification::local_minute_of_day_at(timestamp);
+        format!("{:02}:{:02}", minute_of_day / 60, minute_of_day % 60)
+    };
+
+    let mut focus_time = Duration::ZERO;
+    let mut focus_sessions = 0;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line == session_log::SessionLog::HEADER {
+            continue;
+        }
+        let entry = match line.parse::<session_log::LogEntry>() {
+            Ok(entry) => entry,
+            Err(err) => {
+                eprintln!("warning: skipping line {}: {err}", i + 1);
+                continue;
+            }
+        };
+
+        if entry.activity.is_focus() {
+            focus_time += entry.duration;
+            focus_sessions += 1;
+        }
+        println!(
+            "{}-{}  {:<12} {:>6}",
+            time_of_day(entry.started_at()),
+            time_of_day(entry.completed_at),
+            entry.activity.to_string(),
+            pomodoro::SessionDuration::from(entry.duration).to_string()
+        );
+    }
+
+    let minutes = focus_time.as_secs() / 60;
+    println!(
+        "{focus_sessions} focus sessions, {}h {:02}m focus time",
+        minutes / 60,
+        minutes % 60
+    );
+
+    ExitCode::SUCCESS
+}
+
+/// Reports every failure instead of ignoring it like a session does.
+fn test_notify(notify_backend: NotifyBackend, sound_backend: SoundBackend) -> ExitCode {
+    let mut exit_code = ExitCode::SUCCESS;
+
+    if let Err(err) = notify_backend.notify("corrodoro", "test notification") {
+        eprintln!("{err}");
+        exit_code = ExitCode::FAILURE;
+    }
+    if let Err(err) = sound_backend.play_sync() {
+        eprintln!("{err}");
+        exit_code = ExitCode::FAILURE;
+    }
+
+    exit_code
+}
+
+/// Showing a notification blocks until the backend answers, which is what is measured.
+fn bench_notify(notify_backend: NotifyBackend, count: u32) -> ExitCode {
+    let mut latencies = Vec::new();
+    let mut last_error = None;
+
+    for i in 1..=count {
+        let start = Instant::now();
+        match notify_backend.notify(
+            "corrodoro",
+            &format!("benchmark notification {i} of {count}"),
+        ) {
+            Ok(()) => latencies.push(start.elapsed()),
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    let failed = count as usize - latencies.len();
+    println!("backend: {notify_backend}");
+    println!("notifications: {count}, failed: {failed}");
+    if let (Some(min), Some(max)) = (latencies.iter().min(), latencies.iter().max()) {
+        let average = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+        println!("min: {min:.1?}, avg: {average:.1?}, max: {max:.1?}");
+    }
+    match last_error {
+        Some(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+        None => ExitCode::SUCCESS,
     }
 }
 
 async fn run_offline(
-    work: pomodoro::SessionDuration,
-    short: pomodoro::SessionDuration,
-    long: pomodoro::SessionDuration,
-) -> Result<(), UnrecoverableError> {
-    let settings = pomodoro::Settings::new(work, short, long, false);
-    let state = pomodoro::State::new(settings);
-    let mut app = App::new(state)?;
+    state: pomodoro::State,
+    config: Config,
+) -> Result<RunOutcome, UnrecoverableError> {
+    let mut app = App::new(state, config)?;
 
-    app.run().await?;
+    app.run().await
+}
 
-    Ok(())
+fn print_plan(state: pomodoro::State) {
+    // without long breaks, a cycle is a focus session and a short break
+    let sessions_before_long_break = state.settings().sessions_before_long_break.unwrap_or(1);
+    let mut end_time = pomodoro::SessionDuration::default();
+
+    for (i, (activity, duration)) in state
+        .timeline(2 * sessions_before_long_break as usize)
+        .into_iter()
+        .enumerate()
+    {
+        *end_time += *duration;
+        println!(
+            "{:>3}. {:<12} {:>6}   ends at {:>6}",
+            i + 1,
+            activity.to_string(),
+            duration.to_string(),
+            end_time.to_string()
+        );
+    }
 }
 
 async fn run_client(
     server_addresses: Vec<SocketAddr>,
     ip_version: IpVersion,
+    room: Option<String>,
+    notification_texts: NotificationTexts,
+    tui_config: tui::Config,
+    #[cfg(feature = "socks")] proxy: Option<socks::Proxy>,
 ) -> Result<(), UnrecoverableError> {
     let version_filter = match ip_version {
         IpVersion::V4 => |addr: &&SocketAddr| addr.is_ipv4(),
         IpVersion::V6 => |addr: &&SocketAddr| addr.is_ipv6(),
+        IpVersion::Dual => |_: &&SocketAddr| true,
     };
 
     let server_address = match server_addresses
@@ -79,7 +448,27 @@ async fn run_client(
         },
     };
 
-    let mut app = ClientApp::connect(server_address).await?;
+    // lives as long as the app, which reconnects through it
+    #[cfg(feature = "socks")]
+    let relay = match proxy {
+        Some(proxy) => Some(socks::Relay::start(proxy, server_address).await?),
+        None => None,
+    };
+    #[cfg(feature = "socks")]
+    let connect_to = relay
+        .as_ref()
+        .map_or(server_address, socks::Relay::local_addr);
+    #[cfg(not(feature = "socks"))]
+    let connect_to = server_address;
+
+    let mut app = ClientApp::connect(
+        connect_to,
+        server_address,
+        room,
+        notification_texts,
+        tui_config,
+    )
+    .await?;
 
     app.run().await?;
 
@@ -89,24 +478,34 @@ async fn run_client(
 async fn run_server(
     port: u16,
     ip_version: IpVersion,
-    work: pomodoro::SessionDuration,
-    short: pomodoro::SessionDuration,
-    long: pomodoro::SessionDuration,
-) -> Result<(), UnrecoverableError> {
-    let settings = pomodoro::Settings::new(work, short, long, false);
-    let socket = SocketAddr::new(
-        match ip_version {
-            IpVersion::V4 => Ipv4Addr::UNSPECIFIED.into(),
-            IpVersion::V6 => Ipv6Addr::UNSPECIFIED.into(),
-        },
-        port,
-    );
-    let state = pomodoro::State::new(settings);
-    let mut app = App::new(state)?;
+    state: pomodoro::State,
+    config: Config,
+    #[cfg(feature = "metrics")] metrics_port: Option<u16>,
+    #[cfg(feature = "web")] web_port: Option<u16>,
+) -> Result<RunOutcome, UnrecoverableError> {
+    let ipv4 = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port);
+    let ipv6 = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port);
+    // IPv6 first, in case it also accepts IPv4 connections, see `Servers::host`
+    let sockets = match ip_version {
+        IpVersion::V4 => vec![ipv4],
+        IpVersion::V6 => vec![ipv6],
+        IpVersion::Dual => vec![ipv6, ipv4],
+    };
+    let mut app = App::new(state, config)?;
 
-    app.start_server(socket).await?;
-    app.run().await?;
+    app.start_server(&sockets).await?;
+    #[cfg(feature = "metrics")]
+    if let Some(metrics_port) = metrics_port {
+        app.start_metrics(SocketAddr::new(sockets[0].ip(), metrics_port))
+            .await?;
+    }
+    #[cfg(feature = "web")]
+    if let Some(web_port) = web_port {
+        app.start_web(SocketAddr::new(sockets[0].ip(), web_port))
+            .await?;
+    }
+    let outcome = app.run().await?;
     let _ = app.stop_server().await;
 
-    Ok(())
+    Ok(outcome)
 }
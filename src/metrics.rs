@@ -0,0 +1,195 @@
+use crate::pomodoro::Activity;
+use crate::protocol::TimerVisuals;
+use std::fmt::Write;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Serves the state of a hosted session in the Prometheus text format on `/metrics`.
+/// The exporter shuts down when it is dropped.
+pub struct MetricsExporter {
+    snapshot: watch::Sender<Option<Snapshot>>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Clone, Copy)]
+struct Snapshot {
+    timer_visuals: TimerVisuals,
+    connected_clients: usize,
+}
+
+impl MetricsExporter {
+    pub async fn host(socket: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(socket).await?;
+        let (snapshot, snapshot_receiver) = watch::channel(None);
+        let task = tokio::spawn(serve(listener, snapshot_receiver));
+
+        Ok(Self { snapshot, task })
+    }
+
+    pub fn update(&self, timer_visuals: &TimerVisuals, connected_clients: usize) {
+        self.snapshot.send_replace(Some(Snapshot {
+            timer_visuals: *timer_visuals,
+            connected_clients,
+        }));
+    }
+}
+
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(listener: TcpListener, snapshot: watch::Receiver<Option<Snapshot>>) {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                tracing::debug!(%err, "failed to accept a connection");
+                continue;
+            }
+        };
+        let snapshot = *snapshot.borrow();
+
+        // errors only affect the scraper that caused them, only log them
+        tokio::spawn(async move {
+            match time::timeout(RESPONSE_TIMEOUT, respond(stream, snapshot)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => tracing::debug!(%err, "failed to respond to a scraper"),
+                Err(_) => tracing::debug!("a scraper took too long to send its request"),
+            }
+        });
+    }
+}
+
+/// Longest request line that is read, the rest of a longer request is ignored.
+const MAX_REQUEST_HEAD_SIZE: u64 = 8 * 1024;
+
+/// Connections that don't get through their request and response in time are dropped,
+/// so that idle connections don't hold on to a task forever.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn respond(mut stream: TcpStream, snapshot: Option<Snapshot>) -> io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new((&mut stream).take(MAX_REQUEST_HEAD_SIZE))
+        .read_line(&mut request_line)
+        .await?;
+
+    let response = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/metrics", _] => {
+            let body = snapshot.map(render_metrics).unwrap_or_default();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn render_metrics(snapshot: Snapshot) -> String {
+    let visuals = snapshot.timer_visuals;
+    let mut metrics = String::new();
+
+    let activities = [Activity::Focus, Activity::ShortBreak, Activity::LongBreak].map(|activity| {
+        (
+            format!("{{activity=\"{}\"}}", activity.id()),
+            f64::from(u8::from(visuals.activity == activity)),
+        )
+    });
+    write_gauge(
+        &mut metrics,
+        "corrodoro_activity",
+        "Whether the given activity is the current one.",
+        &activities,
+    );
+    write_gauge(
+        &mut metrics,
+        "corrodoro_remaining_seconds",
+        "Time remaining in the current activity.",
+        &[(String::new(), visuals.time_remaining.as_secs_f64())],
+    );
+    write_gauge(
+        &mut metrics,
+        "corrodoro_timer_paused",
+        "Whether the timer is paused.",
+        &[(String::new(), f64::from(u8::from(visuals.timer_is_paused)))],
+    );
+    write_gauge(
+        &mut metrics,
+        "corrodoro_connected_clients",
+        "Number of connected clients.",
+        &[(String::new(), snapshot.connected_clients as f64)],
+    );
+    write_gauge(
+        &mut metrics,
+        "corrodoro_completed_focus_sessions",
+        "Number of completed focus sessions.",
+        &[(String::new(), f64::from(visuals.completed_focus_sessions))],
+    );
+
+    metrics
+}
+
+/// `samples` consists of pairs of labels (including braces) and values.
+fn write_gauge(metrics: &mut String, name: &str, help: &str, samples: &[(String, f64)]) {
+    // writing to a `String` can't fail
+    let _ = writeln!(metrics, "# HELP {name} {help}");
+    let _ = writeln!(metrics, "# TYPE {name} gauge");
+    for (labels, value) in samples {
+        let _ = writeln!(metrics, "{name}{labels} {value}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn exporter() -> (MetricsExporter, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (snapshot, snapshot_receiver) = watch::channel(None);
+        let exporter = MetricsExporter {
+            snapshot,
+            task: tokio::spawn(serve(listener, snapshot_receiver)),
+        };
+        let stream = TcpStream::connect(addr).await.unwrap();
+
+        (exporter, stream)
+    }
+
+    #[tokio::test]
+    async fn endless_request_lines_are_cut_off() {
+        let (_exporter, mut stream) = exporter().await;
+        // without a newline, the request line would never end
+        stream
+            .write_all(&[b'a'; MAX_REQUEST_HEAD_SIZE as usize])
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        time::timeout(RESPONSE_TIMEOUT, stream.read_to_string(&mut response))
+            .await
+            .expect("the exporter responds before the timeout")
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_connections_are_dropped() {
+        let (_exporter, mut stream) = exporter().await;
+
+        let mut response = Vec::new();
+        let read = time::timeout(2 * RESPONSE_TIMEOUT, stream.read_to_end(&mut response)).await;
+        assert!(matches!(read, Ok(Ok(0))), "the connection is still open");
+    }
+}
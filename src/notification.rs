@@ -1,8 +1,114 @@
+use clap::ValueEnum;
 use rodio::{Decoder, OutputStream, Sink};
+use std::fmt::{Display, Formatter};
 use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
 use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
+/// How notifications are delivered. The tui shows the message as a hint if the backend doesn't show it.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum NotifyBackend {
+    /// Desktop notifications through the system's notification service
+    #[default]
+    Desktop,
+    /// The terminal bell, the message is shown in the tui
+    Bell,
+    /// Only show the message in the tui
+    None,
+}
+
+impl NotifyBackend {
+    pub fn notify(self, title: &str, message: &str) -> Result<(), NotificationError> {
+        match self {
+            NotifyBackend::Desktop => show_desktop_notification(title, message),
+            NotifyBackend::Bell => {
+                play_system_sound();
+                Ok(())
+            }
+            NotifyBackend::None => Ok(()),
+        }
+    }
+
+    /// Whether the message itself is shown to the user.
+    pub fn shows_message(self) -> bool {
+        matches!(self, NotifyBackend::Desktop)
+    }
+}
+
+impl Display for NotifyBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyBackend::Desktop => write!(f, "desktop"),
+            NotifyBackend::Bell => write!(f, "bell"),
+            NotifyBackend::None => write!(f, "none"),
+        }
+    }
+}
+
+/// How the notification sound is played.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum SoundBackend {
+    /// The embedded sound through the default audio output, the terminal bell if there is none
+    #[default]
+    Rodio,
+    /// No sound
+    None,
+}
+
+impl SoundBackend {
+    /// Returns immediately, errors are ignored.
+    pub fn play(self) {
+        match self {
+            SoundBackend::Rodio => play_notification_sound(),
+            SoundBackend::None => {}
+        }
+    }
+
+    /// Like `play`, at a volume that doesn't interrupt, see `--minute-chime`.
+    pub fn play_quietly(self) {
+        match self {
+            SoundBackend::Rodio => play_sound(CHIME_VOLUME),
+            SoundBackend::None => {}
+        }
+    }
+
+    /// Opens the audio output in the background, so that the first sound plays without delay. Failures are only logged.
+    pub fn warm_up(self) {
+        match self {
+            SoundBackend::Rodio => {
+                thread::spawn(|| {
+                    if let Some(audio_output) = audio_output() {
+                        // a silent sound wakes up devices that are suspended while idle
+                        let _ = audio_output.send(0.0);
+                    }
+                });
+            }
+            SoundBackend::None => {}
+        }
+    }
+
+    /// Blocks until the sound has finished playing.
+    pub fn play_sync(self) -> Result<(), NotificationError> {
+        match self {
+            SoundBackend::Rodio => play_notification_sound_sync(),
+            SoundBackend::None => Ok(()),
+        }
+    }
+}
+
+impl Display for SoundBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SoundBackend::Rodio => write!(f, "rodio"),
+            SoundBackend::None => write!(f, "none"),
+        }
+    }
+}
+
 pub fn show_desktop_notification(title: &str, message: &str) -> Result<(), NotificationError> {
     notify_rust::Notification::new()
         .summary(title)
@@ -11,29 +117,260 @@ pub fn show_desktop_notification(title: &str, message: &str) -> Result<(), Notif
     Ok(())
 }
 
+const CHIME_VOLUME: f32 = 0.2;
+
+/// Falls back to the system's sound if there is no usable audio output.
 pub fn play_notification_sound() {
+    play_sound(1.0);
+}
+
+fn play_sound(volume: f32) {
+    // opening the audio output blocks if it wasn't warmed up
     thread::spawn(move || {
-        // ignore errors, too insignificant for crash
-        let _ = play_notification_sound_sync();
+        let played = audio_output().is_some_and(|audio_output| audio_output.send(volume).is_ok());
+        if !played {
+            play_system_sound();
+        }
     });
 }
 
-fn play_notification_sound_sync() -> Result<(), NotificationError> {
+/// The audio output stays open for the whole run in a thread of its own, because opening it takes a moment
+/// and can make the device click. The thread plays the sound at every volume it receives.
+/// `None` if there is no usable audio output, blocks while the output is being opened.
+fn audio_output() -> Option<&'static Sender<f32>> {
+    static AUDIO_OUTPUT: OnceLock<Option<Sender<f32>>> = OnceLock::new();
+
+    AUDIO_OUTPUT
+        .get_or_init(|| {
+            let (volume_sender, volume_receiver) = mpsc::channel::<f32>();
+            let (opened_sender, opened_receiver) = mpsc::channel();
+            thread::spawn(move || {
+                // the stream can't be moved to another thread and stops playing when dropped
+                let (_stream, stream_handle) = match OutputStream::try_default() {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        tracing::info!(%err, "no audio output, ringing the bell instead");
+                        let _ = opened_sender.send(false);
+                        return;
+                    }
+                };
+                let _ = opened_sender.send(true);
+
+                for volume in volume_receiver {
+                    let audio_file =
+                        Cursor::new(include_bytes!(env!("CORRODORO_NOTIFICATION_SOUND_PATH")));
+                    let played = Decoder::new(audio_file)
+                        .map_err(NotificationError::from)
+                        .and_then(|audio| {
+                            let sink = Sink::try_new(&stream_handle)?;
+                            sink.append(audio);
+                            sink.set_volume(volume);
+                            sink.detach();
+                            Ok(())
+                        });
+                    if let Err(err) = played {
+                        tracing::warn!(%err, "failed to play the notification sound");
+                        if volume > 0.0 {
+                            play_system_sound();
+                        }
+                    }
+                }
+            });
+
+            opened_receiver
+                .recv()
+                .unwrap_or(false)
+                .then_some(volume_sender)
+        })
+        .as_ref()
+}
+
+#[cfg(windows)]
+fn play_system_sound() {
+    #[link(name = "user32")]
+    extern "system" {
+        fn MessageBeep(kind: u32) -> i32;
+    }
+    const MB_OK: u32 = 0;
+
+    // SAFETY: `MessageBeep` has no preconditions, it only queues the sound and returns
+    unsafe {
+        MessageBeep(MB_OK);
+    }
+}
+
+/// Rings the terminal bell. Locking stdout keeps it from ending up inside an escape sequence of the tui.
+#[cfg(not(windows))]
+fn play_system_sound() {
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
+
+/// Blocks until the sound has finished playing.
+pub fn play_notification_sound_sync() -> Result<(), NotificationError> {
+    play_sound_sync(1.0)
+}
+
+fn play_sound_sync(volume: f32) -> Result<(), NotificationError> {
     let (_stream, stream_handle) = OutputStream::try_default()?;
-    let audio_file = Cursor::new(include_bytes!("../media/notification.wav"));
+    let audio_file = Cursor::new(include_bytes!(env!("CORRODORO_NOTIFICATION_SOUND_PATH")));
     let audio = Decoder::new(audio_file)?;
 
     let sink = Sink::try_new(&stream_handle)?;
     sink.append(audio);
-    sink.set_volume(1.0);
+    sink.set_volume(volume);
     sink.sleep_until_end();
 
     Ok(())
 }
 
+/// A daily time window in local time during which no notifications are shown or played.
+/// The window may wrap around midnight, e.g. `22:00-07:00`.
+#[derive(Clone, Copy)]
+pub struct QuietHours {
+    /// Minutes since midnight.
+    start: u16,
+    /// Minutes since midnight, exclusive.
+    end: u16,
+}
+
+impl QuietHours {
+    pub fn contain_now(&self) -> bool {
+        self.contain(local_minute_of_day())
+    }
+
+    fn contain(&self, minute_of_day: u16) -> bool {
+        if self.start <= self.end {
+            self.start <= minute_of_day && minute_of_day < self.end
+        } else {
+            minute_of_day >= self.start || minute_of_day < self.end
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseQuietHoursError {
+    #[error("expected \"HH:MM-HH:MM\"")]
+    InvalidFormat,
+    #[error("hours must be less than 24 and minutes less than 60")]
+    InvalidTime,
+    #[error("failed to parse integer: {0}")]
+    ParseIntError(#[from] std::num::ParseIntError),
+    /// The local time is only known on unix, see `local_time_is_known`.
+    #[error("quiet hours need the local time, which is unknown on this system")]
+    LocalTimeUnknown,
+}
+
+impl FromStr for QuietHours {
+    type Err = ParseQuietHoursError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // quiet hours in UTC would silence notifications at the wrong time of day
+        if !local_time_is_known() {
+            return Err(ParseQuietHoursError::LocalTimeUnknown);
+        }
+        let (start, end) = s
+            .split_once('-')
+            .ok_or(ParseQuietHoursError::InvalidFormat)?;
+
+        Ok(QuietHours {
+            start: parse_time_of_day(start)?,
+            end: parse_time_of_day(end)?,
+        })
+    }
+}
+
+/// Parses `HH:MM` into minutes since midnight.
+fn parse_time_of_day(s: &str) -> Result<u16, ParseQuietHoursError> {
+    let (hours, minutes) = s
+        .split_once(':')
+        .ok_or(ParseQuietHoursError::InvalidFormat)?;
+    let (hours, minutes) = (hours.parse::<u16>()?, minutes.parse::<u16>()?);
+    if hours > 23 || minutes > 59 {
+        return Err(ParseQuietHoursError::InvalidTime);
+    }
+
+    Ok(hours * 60 + minutes)
+}
+
+/// Minutes since midnight in the local timezone.
+pub fn local_minute_of_day() -> u16 {
+    local_minute_of_day_at(unix_now())
+}
+
+/// Days since the unix epoch in the local timezone, changes at local midnight.
+pub fn local_day() -> u64 {
+    local_day_at(unix_now())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// `None` if the timestamp can't be converted, e.g. because it is out of range.
+#[cfg(unix)]
+fn local_time(timestamp: u64) -> Option<libc::tm> {
+    let time = libc::time_t::try_from(timestamp).ok()?;
+    // SAFETY: `tm` is plain data, it is only read if `localtime_r` succeeded and wrote it
+    unsafe {
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        let result = libc::localtime_r(&time, &mut tm);
+        (!result.is_null()).then_some(tm)
+    }
+}
+
+#[cfg(unix)]
+fn local_time_is_known() -> bool {
+    local_time(unix_now()).is_some()
+}
+
+/// Minutes since midnight in the local timezone at a unix timestamp in seconds, in UTC if the local time is unknown.
+#[cfg(unix)]
+pub fn local_minute_of_day_at(timestamp: u64) -> u16 {
+    match local_time(timestamp) {
+        Some(tm) => (tm.tm_hour * 60 + tm.tm_min) as u16,
+        None => utc_minute_of_day_at(timestamp),
+    }
+}
+
+#[cfg(unix)]
+fn local_day_at(timestamp: u64) -> u64 {
+    // `c_long` is only 32 bits wide on some platforms
+    #[allow(clippy::useless_conversion)]
+    let utc_offset = local_time(timestamp).map_or(0, |tm| i64::from(tm.tm_gmtoff));
+
+    (timestamp as i64 + utc_offset).div_euclid(24 * 60 * 60) as u64
+}
+
+/// Without a timezone database, times are interpreted in UTC. Quiet hours are rejected for that reason.
+#[cfg(not(unix))]
+fn local_time_is_known() -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub fn local_minute_of_day_at(timestamp: u64) -> u16 {
+    utc_minute_of_day_at(timestamp)
+}
+
+#[cfg(not(unix))]
+fn local_day_at(timestamp: u64) -> u64 {
+    timestamp / (24 * 60 * 60)
+}
+
+fn utc_minute_of_day_at(timestamp: u64) -> u16 {
+    ((timestamp / 60) % (24 * 60)) as u16
+}
+
 #[derive(Debug, Error)]
 pub enum NotificationError {
-    #[error("failed to show desktop notification")]
+    #[error("failed to show desktop notification: {0}")]
     Show(#[from] notify_rust::error::Error),
     #[error("failed to create audio stream for notification sound: {0}")]
     StreamCreation(#[from] rodio::StreamError),
@@ -42,3 +379,47 @@ pub enum NotificationError {
     #[error("failed to decode notification sound: {0}")]
     Decoding(#[from] rodio::decoder::DecoderError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn quiet_hours_may_wrap_around_midnight() {
+        let night: QuietHours = "22:00-07:00".parse().expect("valid quiet hours");
+        assert!(night.contain(22 * 60));
+        assert!(night.contain(0));
+        assert!(night.contain(7 * 60 - 1));
+        assert!(!night.contain(7 * 60));
+        assert!(!night.contain(12 * 60));
+
+        let lunch: QuietHours = "12:00-13:30".parse().expect("valid quiet hours");
+        assert!(lunch.contain(12 * 60 + 45));
+        assert!(!lunch.contain(13 * 60 + 30));
+        assert!(!lunch.contain(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn times_beyond_the_local_calendar_fall_back_to_utc() {
+        // the year doesn't fit into `tm`, `localtime_r` fails
+        let far_future = i64::MAX as u64;
+        assert!(local_time(far_future).is_none());
+        assert!(local_time(u64::MAX).is_none());
+        assert_eq!(
+            local_minute_of_day_at(far_future),
+            utc_minute_of_day_at(far_future)
+        );
+        assert!(local_time_is_known());
+    }
+
+    #[cfg(not(unix))]
+    #[test]
+    fn quiet_hours_are_rejected_without_the_local_time() {
+        assert!(matches!(
+            "22:00-07:00".parse::<QuietHours>(),
+            Err(ParseQuietHoursError::LocalTimeUnknown)
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::strings::strings;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::ops::{Deref, DerefMut};
@@ -10,25 +12,56 @@ pub struct State {
     activity: Activity,
     progress: SessionDuration,
     completed_focus_sessions: u32,
+    /// Focus sessions completed in a row, see `focus_streak`.
+    #[serde(default)]
+    focus_streak: u32,
     timer_is_active: bool,
     settings: Settings,
     current_activity_duration_override: Option<SessionDuration>,
+    /// Remainder of the countdown before a focus session, see `Settings::prepare_duration`.
+    #[serde(default)]
+    preparation_remaining: Duration,
+    /// Time the timer ran during focus sessions and breaks, without countdowns. Not cleared on reset.
+    #[serde(default)]
+    focus_time: Duration,
+    #[serde(default)]
+    break_time: Duration,
 }
 
 impl State {
     pub fn new(settings: Settings) -> State {
+        Self::starting_with(settings, Activity::Focus)
+    }
+
+    /// Starts in the middle of a cycle, e.g. to join others on a break.
+    /// Sessions are counted as usual from there on, so a long break still comes once every `sessions_before_long_break` focus sessions.
+    pub fn starting_with(settings: Settings, activity: Activity) -> State {
         State {
-            activity: Activity::Focus,
+            activity,
             progress: SessionDuration(Duration::from_secs(0)),
             completed_focus_sessions: 0,
+            focus_streak: 0,
             timer_is_active: settings.start_automatically,
             settings,
             current_activity_duration_override: None,
+            preparation_remaining: Duration::ZERO,
+            focus_time: Duration::ZERO,
+            break_time: Duration::ZERO,
         }
     }
 
+    /// Time is spent on the countdown before a focus session first.
     pub fn increase_progress(&mut self, duration: Duration) {
-        *self.progress += duration;
+        let spent_on_preparation = duration.min(self.preparation_remaining);
+        self.preparation_remaining -= spent_on_preparation;
+        *self.progress += duration - spent_on_preparation;
+        // a tick that overshoots the activity counts for it entirely
+        match self.activity {
+            Activity::Focus => self.focus_time += duration - spent_on_preparation,
+            Activity::ShortBreak | Activity::LongBreak => {
+                self.break_time += duration - spent_on_preparation
+            }
+        }
 
         let max_duration = self.current_activity_duration();
 
@@ -45,9 +78,11 @@ impl State {
 
             if self.activity.is_focus() {
                 self.completed_focus_sessions += 1;
+                self.focus_streak += 1;
             }
 
             self.activity = self.next_activity();
+            self.start_preparation();
         }
     }
 
@@ -63,19 +98,27 @@ impl State {
         self.timer_is_active = false;
     }
 
+    /// Starting a focus session that hasn't progressed yet starts the countdown before it again.
     pub fn toggle_timer(&mut self) {
         self.timer_is_active = !self.timer_is_active;
+        if self.progress.is_zero() {
+            self.start_preparation();
+        }
     }
 
     pub fn timer_is_active(&self) -> bool {
         self.timer_is_active
     }
 
+    /// Skipping a focus session counts it as completed, see `end_focus_early` for the alternative.
+    /// It still breaks the focus streak, since the session wasn't focused on until the end.
     pub fn skip_activity(&mut self) {
         self.progress = Duration::from_secs(0).into();
         self.current_activity_duration_override = None;
+        self.preparation_remaining = Duration::ZERO;
         if self.activity.is_focus() {
             self.completed_focus_sessions += 1;
+            self.focus_streak = 0;
         }
 
         if self.settings.start_automatically {
@@ -85,15 +128,49 @@ impl State {
         }
 
         self.activity = self.next_activity();
+        self.start_preparation();
+    }
+
+    /// Ends the current focus session without counting it as completed and starts a short break,
+    /// since a long break has to be earned by completing focus sessions.
+    /// Does nothing if the current activity is not a focus session.
+    pub fn end_focus_early(&mut self) {
+        if !self.activity.is_focus() {
+            return;
+        }
+
+        self.progress = Duration::from_secs(0).into();
+        self.current_activity_duration_override = None;
+        self.preparation_remaining = Duration::ZERO;
+        self.focus_streak = 0;
+
+        if self.settings.start_automatically {
+            self.start_timer();
+        } else {
+            self.stop_timer();
+        }
+
+        self.activity = Activity::ShortBreak;
     }
 
     /// Does nothing if the extension in duration would lead to an overflow (probably about 3.5 billion seconds).
+    /// Does nothing if the activity would become longer than `Settings::max_activity_duration`.
     pub fn extend_activity(&mut self, duration: &Duration) {
-        if let Some(sum) = self.current_activity_duration().checked_add(*duration) {
+        if let Some(sum) = self.extended_activity_duration(duration) {
             self.current_activity_duration_override = Some(sum.into());
         }
     }
 
+    pub fn can_extend_activity(&self, duration: &Duration) -> bool {
+        self.extended_activity_duration(duration).is_some()
+    }
+
+    fn extended_activity_duration(&self, duration: &Duration) -> Option<Duration> {
+        self.current_activity_duration()
+            .checked_add(*duration)
+            .filter(|sum| *sum <= *self.settings.max_activity_duration)
+    }
+
     /// Does nothing if the reducement in duration would yield 0 or lead to a negative duration.
     pub fn reduce_activity(&mut self, duration: &Duration) {
         if *self.time_remaining() > *duration {
@@ -102,38 +179,206 @@ impl State {
         }
     }
 
+    /// Extends the current activity like `extend_activity`, and every later activity of its kind by as much.
+    /// Does nothing if either would become longer than `Settings::max_activity_duration`.
+    pub fn extend_activity_permanently(&mut self, duration: &Duration) {
+        if let (true, Some(sum)) = (
+            self.can_extend_activity(duration),
+            self.extended_default_duration(duration),
+        ) {
+            self.extend_activity(duration);
+            self.set_duration_of(self.activity, sum.into());
+        }
+    }
+
+    pub fn can_extend_activity_permanently(&self, duration: &Duration) -> bool {
+        self.can_extend_activity(duration) && self.extended_default_duration(duration).is_some()
+    }
+
+    fn extended_default_duration(&self, duration: &Duration) -> Option<Duration> {
+        self.settings
+            .duration_of(self.activity)
+            .checked_add(*duration)
+            .filter(|sum| *sum <= *self.settings.max_activity_duration)
+    }
+
+    /// Reduces the current activity like `reduce_activity`, and every later activity of its kind by as much.
+    /// Does nothing if either would reach zero.
+    pub fn reduce_activity_permanently(&mut self, duration: &Duration) {
+        let default_duration = *self.settings.duration_of(self.activity);
+        if *self.time_remaining() > *duration && default_duration > *duration {
+            self.reduce_activity(duration);
+            self.set_duration_of(self.activity, (default_duration - *duration).into());
+        }
+    }
+
+    /// Every focus session from now on lasts this long, the current one too unless it was adjusted.
+    pub fn set_focus_duration(&mut self, duration: SessionDuration) {
+        self.settings.focus_duration = duration;
+    }
+
+    /// See `set_focus_duration`. Replaces `Settings::short_break_ratio`.
+    pub fn set_short_break_duration(&mut self, duration: SessionDuration) {
+        self.settings.short_break_duration = duration;
+        self.settings.short_break_ratio = None;
+    }
+
+    /// See `set_focus_duration`. Replaces `Settings::long_break_ratio`.
+    pub fn set_long_break_duration(&mut self, duration: SessionDuration) {
+        self.settings.long_break_duration = duration;
+        self.settings.long_break_ratio = None;
+    }
+
+    fn set_duration_of(&mut self, activity: Activity, duration: SessionDuration) {
+        match activity {
+            Activity::Focus => self.set_focus_duration(duration),
+            Activity::ShortBreak => self.set_short_break_duration(duration),
+            Activity::LongBreak => self.set_long_break_duration(duration),
+        }
+    }
+
+    /// Moves the timer to the given fraction of the current activity, clamped to `0.0..=1.0`.
+    /// Ends the countdown before a focus session. Does nothing if the fraction is not finite.
+    pub fn set_progress_fraction(&mut self, fraction: f64) {
+        if !fraction.is_finite() {
+            return;
+        }
+        let fraction = fraction.clamp(0.0, 1.0);
+        self.preparation_remaining = Duration::ZERO;
+        self.progress = self.current_activity_duration().mul_f64(fraction).into();
+    }
+
     pub fn progress_percentage(&self) -> f64 {
         self.progress.as_secs_f64() / self.current_activity_duration().as_secs_f64()
     }
 
+    /// The progress once `elapsed` more time has passed, without going past the end of the current activity.
+    /// Used to animate between timer updates, see `Config::smooth_animation`.
+    pub fn progress_percentage_after(&self, elapsed: Duration) -> f64 {
+        let elapsed = elapsed.saturating_sub(self.preparation_remaining);
+        let progress = *self.progress + elapsed.min(*self.time_remaining());
+        progress.as_secs_f64() / self.current_activity_duration().as_secs_f64()
+    }
+
     pub fn current_activity(&self) -> Activity {
         self.activity
     }
 
+    /// Focus sessions that ran out in a row. Skipping or ending a focus session early and resetting break the streak.
+    pub fn focus_streak(&self) -> u32 {
+        self.focus_streak
+    }
+
+    /// Remainder of the countdown before the current focus session, `None` if there is none.
+    pub fn preparation_remaining(&self) -> Option<Duration> {
+        (!self.preparation_remaining.is_zero()).then_some(self.preparation_remaining)
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
     pub fn completed_focus_sessions(&self) -> u32 {
         self.completed_focus_sessions
     }
 
-    fn current_activity_duration(&self) -> SessionDuration {
+    /// Time spent on focus sessions since the state was created.
+    pub fn focus_time(&self) -> Duration {
+        self.focus_time
+    }
+
+    /// Time spent on breaks since the state was created.
+    pub fn break_time(&self) -> Duration {
+        self.break_time
+    }
+
+    /// Time until `focus_sessions` focus sessions will have been completed in total, if the timer runs without pausing.
+    /// Includes the breaks in between. `None` if they already are or if it would take longer than `limit`.
+    pub fn time_until_completed(&self, focus_sessions: u32, limit: Duration) -> Option<Duration> {
+        // bounds the loop even if activities have a duration of zero
+        const MAX_ACTIVITIES: u32 = 10_000;
+
+        let mut state = self.clone();
+        if !state.timer_is_active {
+            state.toggle_timer();
+        }
+        let mut total = Duration::ZERO;
+        for _ in 0..MAX_ACTIVITIES {
+            if state.completed_focus_sessions >= focus_sessions {
+                return (total > Duration::ZERO).then_some(total);
+            }
+            total += *state.time_remaining() + state.preparation_remaining;
+            if total > limit {
+                return None;
+            }
+            state.skip_activity();
+        }
+
+        None
+    }
+
+    /// The current activity and the `count - 1` activities after it, each with its whole duration.
+    /// The current activity keeps its adjustments, the ones after it have their configured durations.
+    /// Doesn't change the state.
+    pub fn timeline(&self, count: usize) -> Vec<(Activity, SessionDuration)> {
+        let mut state = self.clone();
+
+        (0..count)
+            .map(|_| {
+                let entry = (state.activity, state.current_activity_duration());
+                state.skip_activity();
+                entry
+            })
+            .collect()
+    }
+
+    /// Focus sessions left to complete before the long break, including the current one.
+    /// `None` during the long break and without long breaks.
+    /// Matches `next_activity`, which starts the long break once the completed sessions of the cycle reach its position.
+    pub fn focus_sessions_until_long_break(&self) -> Option<u32> {
+        let sessions_before_long_break = self.settings.sessions_before_long_break?;
+        let position = self.settings.long_break_position()?;
+        let completed_in_cycle = self.completed_focus_sessions % sessions_before_long_break;
+
+        (self.activity != Activity::LongBreak).then(|| {
+            (position + sessions_before_long_break - completed_in_cycle - 1)
+                % sessions_before_long_break
+                + 1
+        })
+    }
+
+    /// Returns the number of highlighted indicators and the total number of indicators of the session counter,
+    /// see `session_counter`.
+    pub fn session_counter(&self) -> (u32, u32) {
+        session_counter(
+            self.completed_focus_sessions,
+            self.activity,
+            self.settings.sessions_before_long_break,
+            self.settings.long_break_position(),
+        )
+    }
+
+    /// The duration of the current activity, including adjustments.
+    pub fn current_activity_duration(&self) -> SessionDuration {
         match self.current_activity_duration_override {
             Some(duration) => duration,
-            None => match self.activity {
-                Activity::Focus => self.settings.focus_duration,
-                Activity::ShortBreak => self.settings.short_break_duration,
-                Activity::LongBreak => self.settings.long_break_duration,
-            },
+            None => self.settings.duration_of(self.activity),
         }
     }
 
     fn next_activity(&self) -> Activity {
         match self.activity {
-            Activity::Focus => {
-                if self.completed_focus_sessions % 4 == 0 {
+            Activity::Focus => match (
+                self.settings.sessions_before_long_break,
+                self.settings.long_break_position(),
+            ) {
+                (Some(sessions_before_long_break), Some(position))
+                    if self.completed_focus_sessions % sessions_before_long_break == position =>
+                {
                     Activity::LongBreak
-                } else {
-                    Activity::ShortBreak
                 }
-            }
+                _ => Activity::ShortBreak,
+            },
             Activity::ShortBreak => Activity::Focus,
             Activity::LongBreak => Activity::Focus,
         }
@@ -143,8 +388,23 @@ impl State {
         self.activity = Activity::Focus;
         self.progress = Duration::from_secs(0).into();
         self.completed_focus_sessions = 0;
+        self.focus_streak = 0;
         self.timer_is_active = self.settings.start_automatically;
         self.current_activity_duration_override = None;
+        self.preparation_remaining = Duration::ZERO;
+        self.start_preparation();
+    }
+
+    /// Starts the countdown before the current focus session if the timer is running.
+    /// Progress that was already made, e.g. because a tick overshot the previous activity, counts towards the countdown.
+    fn start_preparation(&mut self) {
+        if !self.timer_is_active || !self.activity.is_focus() {
+            return;
+        }
+
+        let spent_on_preparation = self.progress.min(*self.settings.prepare_duration);
+        self.preparation_remaining = *self.settings.prepare_duration - spent_on_preparation;
+        *self.progress -= spent_on_preparation;
     }
 }
 
@@ -160,7 +420,7 @@ impl Display for State {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
 pub enum Activity {
     Focus,
     ShortBreak,
@@ -171,24 +431,86 @@ impl Activity {
     pub fn is_focus(&self) -> bool {
         matches!(self, Activity::Focus)
     }
+
+    /// A name that doesn't depend on the language, for use in machine-readable output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Activity::Focus => "focus",
+            Activity::ShortBreak => "short_break",
+            Activity::LongBreak => "long_break",
+        }
+    }
+
+    /// The inverse of `id`.
+    pub fn from_id(id: &str) -> Option<Activity> {
+        [Activity::Focus, Activity::ShortBreak, Activity::LongBreak]
+            .into_iter()
+            .find(|activity| activity.id() == id)
+    }
 }
 
 impl Display for Activity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Activity::Focus => write!(f, "focus"),
-            Activity::ShortBreak => write!(f, "short break"),
-            Activity::LongBreak => write!(f, "long break"),
+            Activity::Focus => write!(f, "{}", strings().focus),
+            Activity::ShortBreak => write!(f, "{}", strings().short_break),
+            Activity::LongBreak => write!(f, "{}", strings().long_break),
         }
     }
 }
 
+/// Number of highlighted indicators and total number of indicators of the session counter.
+/// Only the host calls this, clients show the numbers it sends, so that they can't disagree.
+/// Without long breaks, every focus session is a cycle of its own.
+/// The counter's cycle ends with the long break, also if it was moved, see `Settings::long_break_position`.
+pub fn session_counter(
+    completed_focus_sessions: u32,
+    activity: Activity,
+    sessions_before_long_break: Option<u32>,
+    long_break_position: Option<u32>,
+) -> (u32, u32) {
+    let sessions_before_long_break = sessions_before_long_break.unwrap_or(1);
+    let long_break_position = long_break_position.unwrap_or(0) % sessions_before_long_break;
+    let completed_in_cycle = (completed_focus_sessions % sessions_before_long_break
+        + sessions_before_long_break
+        - long_break_position)
+        % sessions_before_long_break;
+    // a cycle is only complete during its long break, a short break may also start a cycle
+    let highlighted = completed_in_cycle
+        + match (activity, completed_in_cycle == 0) {
+            (Activity::Focus, _) => 1,
+            (Activity::LongBreak, true) => sessions_before_long_break,
+            _ => 0,
+        };
+
+    (highlighted, sessions_before_long_break)
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub focus_duration: SessionDuration,
     pub short_break_duration: SessionDuration,
     pub long_break_duration: SessionDuration,
+    /// `None` for no long breaks at all, only short breaks alternate with focus sessions.
+    #[serde(default = "Settings::default_sessions_before_long_break")]
+    pub sessions_before_long_break: Option<u32>,
     pub start_automatically: bool,
+    /// Activities can't be extended beyond this duration.
+    #[serde(default = "Settings::default_max_activity_duration")]
+    pub max_activity_duration: SessionDuration,
+    /// Countdown before a focus session starts, zero for none.
+    #[serde(default)]
+    pub prepare_duration: SessionDuration,
+    /// Replaces `short_break_duration` with this share of the focus duration, which follows changes of the focus duration.
+    #[serde(default)]
+    pub short_break_ratio: Option<BreakRatio>,
+    /// Like `short_break_ratio`, for long breaks.
+    #[serde(default)]
+    pub long_break_ratio: Option<BreakRatio>,
+    /// The focus session of each cycle after which the long break is taken, between 1 and `sessions_before_long_break`.
+    /// `None` takes it after the last one.
+    #[serde(default)]
+    pub long_break_after: Option<u32>,
 }
 
 impl Settings {
@@ -196,29 +518,131 @@ impl Settings {
         focus: SessionDuration,
         short_break: SessionDuration,
         long_break: SessionDuration,
+        sessions_before_long_break: Option<u32>,
         start_automatically: bool,
+        max_activity_duration: SessionDuration,
+        prepare_duration: SessionDuration,
     ) -> Self {
         Settings {
             focus_duration: focus,
             short_break_duration: short_break,
             long_break_duration: long_break,
+            sessions_before_long_break,
             start_automatically,
+            max_activity_duration,
+            prepare_duration,
+            short_break_ratio: None,
+            long_break_ratio: None,
+            long_break_after: None,
+        }
+    }
+
+    /// Configured duration of the activity, without adjustments of the current one.
+    /// Break ratios take precedence over the absolute break durations.
+    pub fn duration_of(&self, activity: Activity) -> SessionDuration {
+        match (activity, self.short_break_ratio, self.long_break_ratio) {
+            (Activity::Focus, _, _) => self.focus_duration,
+            (Activity::ShortBreak, Some(ratio), _) | (Activity::LongBreak, _, Some(ratio)) => {
+                ratio.of(self.focus_duration)
+            }
+            (Activity::ShortBreak, None, _) => self.short_break_duration,
+            (Activity::LongBreak, _, None) => self.long_break_duration,
         }
     }
+
+    /// Number of completed focus sessions within a cycle at which the long break starts, see `long_break_after`.
+    /// Zero stands for the end of the cycle. `None` without long breaks.
+    pub fn long_break_position(&self) -> Option<u32> {
+        let sessions_before_long_break = self.sessions_before_long_break?;
+        let long_break_after = self
+            .long_break_after
+            .unwrap_or(sessions_before_long_break)
+            .clamp(1, sessions_before_long_break);
+
+        Some(long_break_after % sessions_before_long_break)
+    }
+}
+
+/// The only source of the default values, the command line arguments use them as well.
+impl Settings {
+    pub const DEFAULT_FOCUS_DURATION: SessionDuration =
+        SessionDuration(Duration::from_secs(25 * 60));
+    pub const DEFAULT_SHORT_BREAK_DURATION: SessionDuration =
+        SessionDuration(Duration::from_secs(5 * 60));
+    pub const DEFAULT_LONG_BREAK_DURATION: SessionDuration =
+        SessionDuration(Duration::from_secs(20 * 60));
+    pub const DEFAULT_SESSIONS_BEFORE_LONG_BREAK: u32 = 4;
+    pub const DEFAULT_MAX_ACTIVITY_DURATION: SessionDuration =
+        SessionDuration(Duration::from_secs(24 * 60 * 60));
+
+    /// For fields that are missing when deserializing, see `serde(default)`.
+    fn default_sessions_before_long_break() -> Option<u32> {
+        Some(Self::DEFAULT_SESSIONS_BEFORE_LONG_BREAK)
+    }
+
+    fn default_max_activity_duration() -> SessionDuration {
+        Self::DEFAULT_MAX_ACTIVITY_DURATION
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
-            focus_duration: SessionDuration(Duration::from_secs(25 * 60)),
-            short_break_duration: SessionDuration(Duration::from_secs(5 * 60)),
-            long_break_duration: SessionDuration(Duration::from_secs(15 * 60)),
+            focus_duration: Self::DEFAULT_FOCUS_DURATION,
+            short_break_duration: Self::DEFAULT_SHORT_BREAK_DURATION,
+            long_break_duration: Self::DEFAULT_LONG_BREAK_DURATION,
+            sessions_before_long_break: Some(Self::DEFAULT_SESSIONS_BEFORE_LONG_BREAK),
             start_automatically: false,
+            max_activity_duration: Self::DEFAULT_MAX_ACTIVITY_DURATION,
+            prepare_duration: SessionDuration::default(),
+            short_break_ratio: None,
+            long_break_ratio: None,
+            long_break_after: None,
         }
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// A break duration relative to the focus duration, between 0 (exclusive) and 1.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreakRatio(f64);
+
+impl BreakRatio {
+    /// Rounded to whole seconds, so that the break doesn't end a fraction of a second early.
+    pub fn of(self, focus_duration: SessionDuration) -> SessionDuration {
+        let seconds = (focus_duration.as_secs_f64() * self.0).round();
+        SessionDuration(Duration::from_secs(seconds as u64))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseBreakRatioError {
+    #[error("failed to parse number: {0}")]
+    ParseFloatError(#[from] std::num::ParseFloatError),
+    #[error("must be greater than 0 and at most 1, e.g. 0.2 for a fifth of the focus duration")]
+    OutOfRange,
+}
+
+impl FromStr for BreakRatio {
+    type Err = ParseBreakRatioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ratio = s.parse::<f64>()?;
+        // also rejects NaN
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            return Err(ParseBreakRatioError::OutOfRange);
+        }
+
+        Ok(BreakRatio(ratio))
+    }
+}
+
+impl Display for BreakRatio {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct SessionDuration(pub Duration);
 
 impl Deref for SessionDuration {
@@ -290,3 +714,423 @@ impl Display for SessionDuration {
         write!(f, "{minutes}:{seconds:02}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TimerVisuals;
+
+    fn minutes(minutes: u64) -> SessionDuration {
+        SessionDuration(Duration::from_secs(minutes * 60))
+    }
+
+    fn settings(sessions_before_long_break: Option<u32>) -> Settings {
+        Settings::new(
+            minutes(25),
+            minutes(5),
+            minutes(20),
+            sessions_before_long_break,
+            false,
+            Settings::DEFAULT_MAX_ACTIVITY_DURATION,
+            SessionDuration::default(),
+        )
+    }
+
+    /// The activities of the given number of cycles, after which the state is back at the start of a cycle.
+    fn activities(settings: Settings, cycles: usize) -> Vec<Activity> {
+        let sessions = settings.sessions_before_long_break.unwrap_or(1) as usize;
+        State::new(settings)
+            .timeline(2 * sessions * cycles)
+            .into_iter()
+            .map(|(activity, _)| activity)
+            .collect()
+    }
+
+    /// The focus sessions after which a long break is taken, counted from 1.
+    fn long_breaks_after(activities: &[Activity]) -> Vec<usize> {
+        let mut focus_sessions = 0;
+        let mut long_breaks = Vec::new();
+        for activity in activities {
+            match activity {
+                Activity::Focus => focus_sessions += 1,
+                Activity::LongBreak => long_breaks.push(focus_sessions),
+                Activity::ShortBreak => {}
+            }
+        }
+        long_breaks
+    }
+
+    fn with_long_break_after(long_break_after: Option<u32>) -> Settings {
+        Settings {
+            long_break_after,
+            ..settings(Some(4))
+        }
+    }
+
+    #[test]
+    fn long_break_ends_the_cycle_by_default() {
+        assert_eq!(
+            long_breaks_after(&activities(with_long_break_after(None), 3)),
+            [4, 8, 12]
+        );
+        assert_eq!(
+            long_breaks_after(&activities(with_long_break_after(Some(4)), 3)),
+            [4, 8, 12]
+        );
+    }
+
+    #[test]
+    fn long_break_after_the_first_session() {
+        assert_eq!(
+            long_breaks_after(&activities(with_long_break_after(Some(1)), 3)),
+            [1, 5, 9]
+        );
+    }
+
+    #[test]
+    fn long_break_in_the_middle_of_the_cycle() {
+        assert_eq!(
+            long_breaks_after(&activities(with_long_break_after(Some(2)), 3)),
+            [2, 6, 10]
+        );
+    }
+
+    #[test]
+    fn counter_is_full_during_a_moved_long_break() {
+        for long_break_after in 1..=4 {
+            let mut state = State::new(with_long_break_after(Some(long_break_after)));
+            for _ in 0..16 {
+                if state.current_activity() == Activity::LongBreak {
+                    assert_eq!(
+                        state.session_counter(),
+                        (4, 4),
+                        "after session {long_break_after}"
+                    );
+                    assert_eq!(state.focus_sessions_until_long_break(), None);
+                }
+                state.skip_activity();
+            }
+        }
+    }
+
+    #[test]
+    fn sessions_until_a_moved_long_break() {
+        let mut state = State::new(with_long_break_after(Some(2)));
+        assert_eq!(state.focus_sessions_until_long_break(), Some(2));
+        state.skip_activity();
+        state.skip_activity();
+        assert_eq!(state.focus_sessions_until_long_break(), Some(1));
+        assert_eq!(state.session_counter(), (4, 4));
+    }
+
+    #[test]
+    fn sessions_until_long_break_count_down_to_it() {
+        for cadence in 1..=6 {
+            let mut state = State::new(settings(Some(cadence)));
+            let mut expected = cadence;
+            for _ in 0..2 * 2 * cadence {
+                let activity = state.current_activity();
+                if activity == Activity::LongBreak {
+                    assert_eq!(state.focus_sessions_until_long_break(), None);
+                    expected = cadence;
+                    complete_activity(&mut state);
+                    continue;
+                }
+
+                assert_eq!(
+                    state.focus_sessions_until_long_break(),
+                    Some(expected),
+                    "during a {activity:?} of a {cadence} session cycle"
+                );
+                complete_activity(&mut state);
+                // the count matches what the timer actually does next
+                if activity == Activity::Focus {
+                    let long_break_started = state.current_activity() == Activity::LongBreak;
+                    assert_eq!(long_break_started, expected == 1);
+                    expected -= u32::from(!long_break_started);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_timeline() {
+        assert!(State::new(settings(Some(4))).timeline(0).is_empty());
+    }
+
+    #[test]
+    fn timeline_wraps_around_cycles() {
+        let timeline = State::new(settings(Some(2))).timeline(9);
+        assert_eq!(
+            timeline,
+            [
+                (Activity::Focus, minutes(25)),
+                (Activity::ShortBreak, minutes(5)),
+                (Activity::Focus, minutes(25)),
+                (Activity::LongBreak, minutes(20)),
+                (Activity::Focus, minutes(25)),
+                (Activity::ShortBreak, minutes(5)),
+                (Activity::Focus, minutes(25)),
+                (Activity::LongBreak, minutes(20)),
+                (Activity::Focus, minutes(25)),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeline_of_single_session_cycles() {
+        let activities: Vec<_> = State::new(settings(Some(1)))
+            .timeline(6)
+            .into_iter()
+            .map(|(activity, _)| activity)
+            .collect();
+        assert_eq!(
+            activities,
+            [
+                Activity::Focus,
+                Activity::LongBreak,
+                Activity::Focus,
+                Activity::LongBreak,
+                Activity::Focus,
+                Activity::LongBreak,
+            ]
+        );
+    }
+
+    #[test]
+    fn timeline_without_long_breaks() {
+        let timeline = State::new(settings(None)).timeline(20);
+        assert!(timeline
+            .iter()
+            .all(|(activity, _)| *activity != Activity::LongBreak));
+        assert_eq!(timeline[1], (Activity::ShortBreak, minutes(5)));
+    }
+
+    #[test]
+    fn no_long_break_without_a_cycle() {
+        let mut state = State::new(settings(None));
+        for step in 0..40 {
+            assert_ne!(state.current_activity(), Activity::LongBreak);
+            assert_eq!(state.focus_sessions_until_long_break(), None);
+            assert_eq!(state.session_counter().1, 1);
+            // completing and skipping take different paths to the next activity
+            if step % 3 == 0 {
+                state.skip_activity();
+            } else {
+                complete_activity(&mut state);
+            }
+        }
+        assert!(state.completed_focus_sessions() >= 20);
+    }
+
+    #[test]
+    fn timeline_starts_in_the_middle_of_a_cycle() {
+        let mut state = State::new(settings(Some(4)));
+        for _ in 0..5 {
+            state.skip_activity();
+        }
+        let activities: Vec<_> = state
+            .timeline(4)
+            .into_iter()
+            .map(|(activity, _)| activity)
+            .collect();
+        assert_eq!(
+            activities,
+            [
+                Activity::ShortBreak,
+                Activity::Focus,
+                Activity::LongBreak,
+                Activity::Focus,
+            ]
+        );
+    }
+
+    #[test]
+    fn adjustments_of_the_current_activity_stay_in_the_first_entry() {
+        let mut state = State::new(settings(Some(4)));
+        state.extend_activity(&Duration::from_secs(10 * 60));
+        state.set_progress_fraction(0.5);
+        let remaining_before = state.time_remaining();
+
+        let timeline = state.timeline(4);
+
+        // whole durations, regardless of the progress
+        assert_eq!(timeline[0], (Activity::Focus, minutes(35)));
+        assert_eq!(timeline[1], (Activity::ShortBreak, minutes(5)));
+        assert_eq!(timeline[2], (Activity::Focus, minutes(25)));
+        // the state itself didn't move on
+        assert_eq!(state.current_activity(), Activity::Focus);
+        assert_eq!(state.time_remaining(), remaining_before);
+        assert_eq!(state.completed_focus_sessions(), 0);
+    }
+
+    #[test]
+    fn permanent_adjustments_apply_to_later_entries() {
+        let mut state = State::new(settings(Some(4)));
+        state.extend_activity_permanently(&Duration::from_secs(5 * 60));
+
+        let timeline = state.timeline(3);
+
+        assert_eq!(timeline[0], (Activity::Focus, minutes(30)));
+        assert_eq!(timeline[2], (Activity::Focus, minutes(30)));
+    }
+
+    #[test]
+    fn activities_are_extended_up_to_the_max_duration() {
+        let mut state = State::new(Settings {
+            max_activity_duration: minutes(40),
+            ..settings(Some(4))
+        });
+        let five_minutes = Duration::from_secs(5 * 60);
+
+        for _ in 0..3 {
+            assert!(state.can_extend_activity(&five_minutes));
+            state.extend_activity(&five_minutes);
+        }
+        assert_eq!(state.current_activity_duration(), minutes(40));
+
+        assert!(!state.can_extend_activity(&Duration::from_secs(1)));
+        state.extend_activity(&Duration::from_secs(1));
+        assert_eq!(state.current_activity_duration(), minutes(40));
+
+        assert!(!state.can_extend_activity_permanently(&five_minutes));
+        state.extend_activity_permanently(&five_minutes);
+        assert_eq!(state.current_activity_duration(), minutes(40));
+        assert_eq!(state.settings().duration_of(Activity::Focus), minutes(25));
+    }
+
+    #[test]
+    fn permanent_extensions_respect_the_max_duration_of_later_activities() {
+        let mut state = State::new(Settings {
+            max_activity_duration: minutes(30),
+            ..settings(Some(4))
+        });
+        state.reduce_activity(&Duration::from_secs(10 * 60));
+
+        // the current activity would only be 25 minutes long, but later ones 35
+        state.extend_activity_permanently(&Duration::from_secs(10 * 60));
+        assert_eq!(state.current_activity_duration(), minutes(15));
+        assert_eq!(state.settings().duration_of(Activity::Focus), minutes(25));
+    }
+
+    /// Lets the current activity run out.
+    fn complete_activity(state: &mut State) {
+        state.increase_progress(*state.time_remaining());
+    }
+
+    #[test]
+    fn completed_focus_sessions_extend_the_streak() {
+        let mut state = State::new(settings(Some(4)));
+        for _ in 0..2 * 4 {
+            complete_activity(&mut state);
+        }
+        assert_eq!(state.focus_streak(), 4);
+        assert_eq!(state.completed_focus_sessions(), 4);
+        assert_eq!(TimerVisuals::from(&state).focus_streak, 4);
+    }
+
+    #[test]
+    fn skipping_breaks_only_the_streak_of_focus_sessions() {
+        let mut state = State::new(settings(Some(4)));
+        complete_activity(&mut state);
+        // skipping the short break
+        state.skip_activity();
+        assert_eq!(state.focus_streak(), 1);
+
+        state.skip_activity();
+        assert_eq!(state.focus_streak(), 0);
+        // the skipped focus session still counts as completed
+        assert_eq!(state.completed_focus_sessions(), 2);
+
+        state.skip_activity();
+        complete_activity(&mut state);
+        assert_eq!(state.focus_streak(), 1);
+    }
+
+    #[test]
+    fn ending_focus_early_and_resetting_break_the_streak() {
+        let mut state = State::new(settings(Some(4)));
+        complete_activity(&mut state);
+        complete_activity(&mut state);
+        state.end_focus_early();
+        assert_eq!(state.focus_streak(), 0);
+        assert_eq!(state.completed_focus_sessions(), 1);
+
+        complete_activity(&mut state);
+        complete_activity(&mut state);
+        assert_eq!(state.focus_streak(), 1);
+        state.reset();
+        assert_eq!(state.focus_streak(), 0);
+    }
+
+    #[test]
+    fn focus_and_break_time_add_up_separately() {
+        let mut state = State::new(settings(Some(4)));
+        state.increase_progress(Duration::from_secs(10 * 60));
+        assert_eq!(state.focus_time(), Duration::from_secs(10 * 60));
+        assert_eq!(state.break_time(), Duration::ZERO);
+
+        // a tick that overshoots the focus session counts for it entirely
+        state.increase_progress(Duration::from_secs(16 * 60));
+        assert_eq!(state.current_activity(), Activity::ShortBreak);
+        assert_eq!(state.focus_time(), Duration::from_secs(26 * 60));
+        assert_eq!(state.break_time(), Duration::ZERO);
+
+        state.increase_progress(Duration::from_secs(3 * 60));
+        assert_eq!(state.focus_time(), Duration::from_secs(26 * 60));
+        assert_eq!(state.break_time(), Duration::from_secs(3 * 60));
+
+        state.reset();
+        assert_eq!(state.focus_time(), Duration::from_secs(26 * 60));
+        assert_eq!(state.break_time(), Duration::from_secs(3 * 60));
+    }
+
+    #[test]
+    fn countdowns_are_neither_focus_nor_break_time() {
+        let mut state = State::new(Settings {
+            prepare_duration: SessionDuration(Duration::from_secs(10)),
+            ..settings(Some(4))
+        });
+        state.toggle_timer();
+        state.increase_progress(Duration::from_secs(15));
+
+        assert_eq!(state.focus_time(), Duration::from_secs(5));
+        assert_eq!(state.break_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn session_counter_for_every_point_of_a_cycle() {
+        use Activity::{Focus, LongBreak, ShortBreak};
+        // (completed focus sessions, activity, sessions before the long break, long break position, expected)
+        let cases = [
+            (0, Focus, Some(4), Some(0), (1, 4)),
+            (1, ShortBreak, Some(4), Some(0), (1, 4)),
+            (1, Focus, Some(4), Some(0), (2, 4)),
+            (3, Focus, Some(4), Some(0), (4, 4)),
+            (4, LongBreak, Some(4), Some(0), (4, 4)),
+            (4, ShortBreak, Some(4), Some(0), (0, 4)),
+            (4, Focus, Some(4), Some(0), (1, 4)),
+            (7, ShortBreak, Some(4), Some(0), (3, 4)),
+            // the long break after the second session of each cycle
+            (0, Focus, Some(4), Some(2), (3, 4)),
+            (1, Focus, Some(4), Some(2), (4, 4)),
+            (2, LongBreak, Some(4), Some(2), (4, 4)),
+            (2, Focus, Some(4), Some(2), (1, 4)),
+            (5, ShortBreak, Some(4), Some(2), (3, 4)),
+            // without long breaks
+            (0, Focus, None, None, (1, 1)),
+            (3, ShortBreak, None, None, (0, 1)),
+            (3, Focus, None, None, (1, 1)),
+        ];
+
+        for (completed, activity, sessions_before_long_break, long_break_position, expected) in
+            cases
+        {
+            assert_eq!(
+                session_counter(completed, activity, sessions_before_long_break, long_break_position),
+                expected,
+                "{completed} completed, {activity:?}, {sessions_before_long_break:?}, {long_break_position:?}"
+            );
+        }
+    }
+}
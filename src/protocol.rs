@@ -1,5 +1,6 @@
 use crate::pomodoro::{Activity, SessionDuration};
-use bincode::{deserialize, serialize};
+use crate::strings::strings;
+use bincode::Options;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -10,34 +11,195 @@ pub enum NetworkProtocol {
     Event(Event),
     Display(TimerVisuals),
     Notify(Activity),
+    /// Sent when an activity was skipped, as opposed to `Notify` which is sent when an activity was completed.
+    NotifySkip(Activity),
+    /// Sent by clients to share a separate timer with the other clients in the room of the given name.
+    /// The empty name refers to the host's timer, which clients share by default.
+    JoinRoom(String),
+    /// Sent when the remaining time was changed by hand, so that everyone sees who changed it.
+    NotifyAdjustment(Adjustment),
+    /// Sent by clients that want the current frame of their timer right away, the host answers with `Display`.
+    RequestSync,
+    /// Sent by clients first thing after connecting. The token stays the same when a client reconnects,
+    /// so that the host can tell a reconnection from a new client.
+    Resume(u64),
+    /// A line of text from the host for every client, see `sanitize_announcement`.
+    Announce(String),
+    /// Sent by the host after connecting, the longest time between two frames while its timer runs.
+    /// Clients use it to tell a stalled connection from a slow host, see `ClientApp::stale_after`.
+    FrameInterval(Duration),
+}
+
+/// Longest announcement in characters, so that it fits the title of the timer panel.
+pub const MAX_ANNOUNCEMENT_LENGTH: usize = 80;
+
+/// Removes control characters, which could mess up the terminal, and cuts the text to `MAX_ANNOUNCEMENT_LENGTH`.
+/// Announcements are sanitized when they are typed and again when they are received.
+pub fn sanitize_announcement(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_ANNOUNCEMENT_LENGTH)
+        .collect();
+    text.trim().to_string()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Adjustment {
+    pub extended: bool,
+    pub amount: Duration,
+    /// Id of the client, `None` stands for the host.
+    pub by: Option<String>,
+    /// The new duration of every later activity of the adjusted kind, `None` if only the current activity changed.
+    pub new_default: Option<(Activity, SessionDuration)>,
+}
+
+impl Adjustment {
+    pub fn text(&self) -> String {
+        let change = format!(
+            "{}{}",
+            if self.extended { "+" } else { "-" },
+            SessionDuration(self.amount)
+        );
+        let by = self.by.as_deref().unwrap_or(strings().host);
+
+        match self.new_default {
+            Some((activity, duration)) => (strings().adjusted_permanently)(
+                &change,
+                &activity.to_string(),
+                &duration.to_string(),
+                by,
+            ),
+            None => (strings().adjusted)(&change, by),
+        }
+    }
+}
+
+/// Upper bound for the size of a serialized message.
+/// Messages are tiny, this only guards against malformed frames announcing huge allocations.
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024;
+
+/// Same encoding as `bincode::serialize`, but with a size limit.
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_MESSAGE_SIZE)
 }
 
 impl Message for NetworkProtocol {
     fn serialize(&self) -> Result<Bytes, MessageError> {
-        Ok(serialize(self).map_err(|_| MessageError::Serialize)?.into())
+        Ok(bincode_options()
+            .serialize(self)
+            .map_err(|_| MessageError::Serialize)?
+            .into())
     }
 
     fn deserialize(bytes: Bytes) -> Result<Self, MessageError> {
-        deserialize(&bytes).map_err(|_| MessageError::Deserialize)
+        if bytes.len() as u64 > MAX_MESSAGE_SIZE {
+            return Err(MessageError::Deserialize);
+        }
+
+        bincode_options()
+            .deserialize(&bytes)
+            .map_err(|_| MessageError::Deserialize)
     }
 }
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
 pub enum Event {
     Quit,
     ToggleTimer,
     ResetTimer,
+    /// Counts a skipped focus session as completed.
     SkipActivity,
+    /// Does not count the ended focus session as completed.
+    EndFocusEarly,
     ExtendActivity(Duration),
     ReduceActivity(Duration),
+    /// Moves the timer to the given fraction of the current activity, between 0 and 1.
+    SetProgress(f64),
+    /// Asks the host for the current frame, see `NetworkProtocol::RequestSync`. Does nothing on the host.
+    RequestSync,
+    /// Like `ExtendActivity`, but also for every later activity of the same kind.
+    ExtendActivityPermanently(Duration),
+    /// Like `ReduceActivity`, but also for every later activity of the same kind.
+    ReduceActivityPermanently(Duration),
+    /// Sends the text to every client, see `NetworkProtocol::Announce`. Only accepted from the host's own tui.
+    Announce(String),
 }
 
 // TODO: change (remaining time, progress percentage) to (progressed time) and (total time)
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct TimerVisuals {
     pub time_remaining: SessionDuration,
     pub timer_is_paused: bool,
     pub activity: Activity,
     pub progress_percentage: f64,
+    /// Remainder of the countdown before a focus session starts.
+    pub preparation_remaining: Option<Duration>,
     pub completed_focus_sessions: u32,
+    pub focus_streak: u32,
+    /// Computed by the host, so that clients don't have to know the host's settings.
+    pub session_counter_highlighted: u32,
+    pub session_counter_total: u32,
+    pub focus_sessions_until_long_break: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NetworkProtocol, MAX_MESSAGE_SIZE};
+    use bytes::Bytes;
+    use zwiesel::{Message, MessageError};
+
+    /// Index of `NetworkProtocol::Announce`, which bincode writes in front of it.
+    const ANNOUNCE: u32 = 8;
+
+    /// An announcement of "hi" whose length prefix may claim more bytes than were sent.
+    fn announce_with_length(length: u64) -> Bytes {
+        let mut bytes = ANNOUNCE.to_le_bytes().to_vec();
+        bytes.extend(length.to_le_bytes());
+        bytes.extend(b"hi");
+        bytes.into()
+    }
+
+    #[test]
+    fn announcements_survive_the_round_trip() {
+        let bytes = NetworkProtocol::Announce("hi".to_string())
+            .serialize()
+            .expect("announcements can be serialized");
+        assert_eq!(bytes, announce_with_length(2));
+        assert!(matches!(
+            NetworkProtocol::deserialize(bytes),
+            Ok(NetworkProtocol::Announce(text)) if text == "hi"
+        ));
+    }
+
+    #[test]
+    fn oversized_length_prefixes_are_rejected() {
+        for length in [MAX_MESSAGE_SIZE, u64::MAX] {
+            assert!(matches!(
+                NetworkProtocol::deserialize(announce_with_length(length)),
+                Err(MessageError::Deserialize)
+            ));
+        }
+    }
+
+    #[test]
+    fn oversized_messages_are_rejected() {
+        let text = "a".repeat(MAX_MESSAGE_SIZE as usize);
+        assert!(matches!(
+            NetworkProtocol::Announce(text.clone()).serialize(),
+            Err(MessageError::Serialize)
+        ));
+
+        let mut bytes = ANNOUNCE.to_le_bytes().to_vec();
+        bytes.extend((text.len() as u64).to_le_bytes());
+        bytes.extend(text.as_bytes());
+        assert!(matches!(
+            NetworkProtocol::deserialize(bytes.into()),
+            Err(MessageError::Deserialize)
+        ));
+    }
 }
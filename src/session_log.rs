@@ -0,0 +1,94 @@
+use crate::pomodoro::Activity;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::num::ParseIntError;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Appends a CSV line for every completed activity to a file.
+/// Each line is flushed right away, so that nothing is lost if the program is killed.
+pub struct SessionLog {
+    writer: BufWriter<File>,
+}
+
+impl SessionLog {
+    pub const HEADER: &'static str = "timestamp,activity,duration_seconds";
+
+    /// Creates the file if it doesn't exist yet, otherwise appends to it.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut log = Self {
+            writer: BufWriter::new(file),
+        };
+
+        if is_empty {
+            writeln!(log.writer, "{}", Self::HEADER)?;
+            log.writer.flush()?;
+        }
+
+        Ok(log)
+    }
+
+    /// `duration` is the planned duration of the activity, including adjustments.
+    pub fn record(&mut self, activity: Activity, duration: Duration) -> io::Result<()> {
+        // a clock before 1970 is not worth failing over
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        writeln!(
+            self.writer,
+            "{timestamp},{},{}",
+            activity.id(),
+            duration.as_secs()
+        )?;
+        self.writer.flush()
+    }
+}
+
+/// A line of a session log.
+pub struct LogEntry {
+    /// Unix timestamp in seconds at which the activity was completed.
+    pub completed_at: u64,
+    pub activity: Activity,
+    pub duration: Duration,
+}
+
+impl LogEntry {
+    pub fn started_at(&self) -> u64 {
+        self.completed_at.saturating_sub(self.duration.as_secs())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseLogEntryError {
+    #[error("expected 3 comma-separated fields")]
+    FieldCount,
+    #[error("invalid timestamp: {0}")]
+    Timestamp(ParseIntError),
+    #[error("unknown activity {0:?}")]
+    Activity(String),
+    #[error("invalid duration: {0}")]
+    Duration(ParseIntError),
+}
+
+impl FromStr for LogEntry {
+    type Err = ParseLogEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [timestamp, activity, duration] = s.trim().split(',').collect::<Vec<_>>()[..] else {
+            return Err(ParseLogEntryError::FieldCount);
+        };
+
+        Ok(LogEntry {
+            completed_at: timestamp.parse().map_err(ParseLogEntryError::Timestamp)?,
+            activity: Activity::from_id(activity)
+                .ok_or_else(|| ParseLogEntryError::Activity(activity.to_string()))?,
+            duration: Duration::from_secs(duration.parse().map_err(ParseLogEntryError::Duration)?),
+        })
+    }
+}
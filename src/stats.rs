@@ -0,0 +1,457 @@
+use crate::notification;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::warn;
+
+/// Focus statistics accumulated over all runs, stored in the data directory of the user.
+/// The file consists of `key=value` lines and starts with a version, so that it can evolve.
+/// Every day with focus sessions is a `day=DAY,SESSIONS,SECONDS` line, `DAY` counts local days since the unix epoch.
+#[derive(Default)]
+pub struct LifetimeStats {
+    pub focus_time: Duration,
+    pub completed_focus_sessions: u64,
+    /// Of completed breaks.
+    pub break_time: Duration,
+    /// By local day, see `notification::local_day`.
+    days: BTreeMap<u64, DayStats>,
+    /// Recorded since the file was read, added to what the file contains when saving,
+    /// so that instances that run at the same time don't overwrite each other's sessions.
+    unsaved: Unsaved,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct DayStats {
+    pub completed_focus_sessions: u64,
+    pub focus_time: Duration,
+}
+
+#[derive(Default)]
+struct Unsaved {
+    /// By local day, like `LifetimeStats::days`.
+    focus_sessions: Vec<(u64, Duration)>,
+    break_time: Duration,
+}
+
+impl LifetimeStats {
+    /// Version 1 only kept the number of focus sessions of the last day with focus sessions.
+    const VERSION: u32 = 2;
+    const FILE_NAME: &'static str = "stats";
+    /// Where a file that can't be read is moved, so that it isn't overwritten and can be repaired by hand.
+    const CORRUPT_FILE_NAME: &'static str = "stats.corrupt";
+    /// Version of the format of `to_json`, increased whenever a field changes or disappears.
+    pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+    /// A missing file yields empty statistics, a corrupt one is moved aside, see `CORRUPT_FILE_NAME`.
+    /// Files written by a newer version are an error.
+    pub fn load() -> Result<Self, StatsError> {
+        Self::load_from(&Self::path().ok_or(StatsError::NoDataDirectory)?)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, StatsError> {
+        let parsed = match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            // also if the file isn't valid UTF-8
+            Err(err) => {
+                warn!(%err, path = %path.display(), "failed to read the statistics");
+                None
+            }
+        };
+        if let Some(stats) = parsed {
+            return Ok(stats);
+        }
+
+        let corrupt_path = path.with_file_name(Self::CORRUPT_FILE_NAME);
+        fs::rename(path, &corrupt_path)
+            .map_err(|err| StatsError::MoveAside(path.to_path_buf(), err))?;
+        warn!(path = %corrupt_path.display(), "moved the corrupt statistics aside");
+
+        Ok(Self::default())
+    }
+
+    /// `None` if the content is not a statistics file or a value doesn't parse.
+    fn parse(content: &str) -> Result<Option<Self>, StatsError> {
+        let mut stats = Self::default();
+        let mut version = None;
+        let mut last_focus_day = None;
+        let mut focus_sessions_on_last_focus_day = 0;
+        for (key, value) in content.lines().filter_map(|line| line.split_once('=')) {
+            let parsed = match key {
+                "version" => value.parse().map(|v| version = Some(v)).is_ok(),
+                "focus_seconds" => value
+                    .parse()
+                    .map(|seconds| stats.focus_time = Duration::from_secs(seconds))
+                    .is_ok(),
+                "completed_focus_sessions" => value
+                    .parse()
+                    .map(|sessions| stats.completed_focus_sessions = sessions)
+                    .is_ok(),
+                "break_seconds" => value
+                    .parse()
+                    .map(|seconds| stats.break_time = Duration::from_secs(seconds))
+                    .is_ok(),
+                "day" => parse_day(value)
+                    .map(|(day, day_stats)| stats.days.insert(day, day_stats))
+                    .is_some(),
+                "last_focus_day" => value.parse().map(|day| last_focus_day = Some(day)).is_ok(),
+                "focus_sessions_on_last_focus_day" => value
+                    .parse()
+                    .map(|sessions| focus_sessions_on_last_focus_day = sessions)
+                    .is_ok(),
+                _ => true,
+            };
+            if !parsed {
+                return Ok(None);
+            }
+        }
+
+        match version {
+            Some(Self::VERSION) => Ok(Some(stats)),
+            // the focus time of that day is unknown
+            Some(1) => {
+                if let Some(day) = last_focus_day.filter(|_| focus_sessions_on_last_focus_day > 0) {
+                    stats.days.insert(
+                        day,
+                        DayStats {
+                            completed_focus_sessions: focus_sessions_on_last_focus_day,
+                            focus_time: Duration::ZERO,
+                        },
+                    );
+                }
+                Ok(Some(stats))
+            }
+            Some(version) if version > Self::VERSION => {
+                Err(StatsError::UnsupportedVersion(version))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads the file again and adds what was recorded since, then replaces the file atomically,
+    /// so that a crash can't leave a half-written file behind. Afterwards, the statistics include
+    /// the sessions that other instances saved in the meantime.
+    pub fn save(&mut self) -> Result<(), StatsError> {
+        self.save_to(&Self::path().ok_or(StatsError::NoDataDirectory)?)
+    }
+
+    fn save_to(&mut self, path: &Path) -> Result<(), StatsError> {
+        let mut stats = Self::load_from(path)?;
+        for &(day, duration) in &self.unsaved.focus_sessions {
+            stats.add_focus_session(day, duration);
+        }
+        stats.break_time += self.unsaved.break_time;
+
+        let mut content = format!(
+            "version={}\nfocus_seconds={}\ncompleted_focus_sessions={}\nbreak_seconds={}\n",
+            Self::VERSION,
+            stats.focus_time.as_secs(),
+            stats.completed_focus_sessions,
+            stats.break_time.as_secs(),
+        );
+        for (day, day_stats) in &stats.days {
+            content.push_str(&format!(
+                "day={day},{},{}\n",
+                day_stats.completed_focus_sessions,
+                day_stats.focus_time.as_secs()
+            ));
+        }
+        let write = || -> io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temporary_path = path.with_extension("tmp");
+            fs::write(&temporary_path, &content)?;
+            fs::rename(temporary_path, path)
+        };
+        write().map_err(|err| StatsError::Write(path.to_path_buf(), err))?;
+        *self = stats;
+
+        Ok(())
+    }
+
+    pub fn record_focus_session(&mut self, duration: Duration) {
+        let today = notification::local_day();
+        self.add_focus_session(today, duration);
+        self.unsaved.focus_sessions.push((today, duration));
+    }
+
+    pub fn record_break(&mut self, duration: Duration) {
+        self.break_time += duration;
+        self.unsaved.break_time += duration;
+    }
+
+    fn add_focus_session(&mut self, day: u64, duration: Duration) {
+        let day_stats = self.days.entry(day).or_default();
+        day_stats.completed_focus_sessions += 1;
+        day_stats.focus_time += duration;
+        self.focus_time += duration;
+        self.completed_focus_sessions += 1;
+    }
+
+    pub fn focus_sessions_today(&self) -> u64 {
+        self.days
+            .get(&notification::local_day())
+            .map_or(0, |today| today.completed_focus_sessions)
+    }
+
+    /// Consecutive days with focus sessions up to today. Today doesn't break the streak until it is over.
+    pub fn current_streak(&self) -> u64 {
+        let today = notification::local_day();
+        let mut day = match self.days.contains_key(&today) {
+            true => today,
+            false => today.saturating_sub(1),
+        };
+        let mut streak = 0;
+        while self.days.contains_key(&day) {
+            streak += 1;
+            let Some(previous_day) = day.checked_sub(1) else {
+                break;
+            };
+            day = previous_day;
+        }
+        streak
+    }
+
+    pub fn longest_streak(&self) -> u64 {
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut previous_day = None;
+        for &day in self.days.keys() {
+            streak = match previous_day {
+                Some(previous_day) if previous_day + 1 == day => streak + 1,
+                _ => 1,
+            };
+            longest = longest.max(streak);
+            previous_day = Some(day);
+        }
+        longest
+    }
+
+    /// A summary for other tools, see `EXPORT_SCHEMA_VERSION`. Days are local dates, oldest first.
+    pub fn to_json(&self) -> String {
+        let days = self
+            .days
+            .iter()
+            .map(|(day, day_stats)| {
+                format!(
+                    "{{\"date\":\"{}\",\"completed_focus_sessions\":{},\"focus_seconds\":{}}}",
+                    date(*day),
+                    day_stats.completed_focus_sessions,
+                    day_stats.focus_time.as_secs()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"schema_version\":{},\"focus_seconds\":{},\"break_seconds\":{},\"completed_focus_sessions\":{},\"completed_focus_sessions_today\":{},\"current_streak_days\":{},\"longest_streak_days\":{},\"days\":[{days}]}}",
+            Self::EXPORT_SCHEMA_VERSION,
+            self.focus_time.as_secs(),
+            self.break_time.as_secs(),
+            self.completed_focus_sessions,
+            self.focus_sessions_today(),
+            self.current_streak(),
+            self.longest_streak(),
+        )
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join("corrodoro").join(Self::FILE_NAME))
+    }
+}
+
+/// Parses the value of a `day` line, see `LifetimeStats`.
+fn parse_day(value: &str) -> Option<(u64, DayStats)> {
+    let mut fields = value.split(',').map(|field| field.parse::<u64>().ok());
+    let (Some(Some(day)), Some(Some(sessions)), Some(Some(seconds)), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return None;
+    };
+
+    Some((
+        day,
+        DayStats {
+            completed_focus_sessions: sessions,
+            focus_time: Duration::from_secs(seconds),
+        },
+    ))
+}
+
+/// Formats days since the unix epoch as `YYYY-MM-DD`, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn date(day: u64) -> String {
+    let days = day as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+impl Display for LifetimeStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let minutes = self.focus_time.as_secs() / 60;
+        writeln!(f, "focus time: {}h {:02}m", minutes / 60, minutes % 60)?;
+        writeln!(
+            f,
+            "completed focus sessions: {}",
+            self.completed_focus_sessions
+        )?;
+        writeln!(
+            f,
+            "completed focus sessions today: {}",
+            self.focus_sessions_today()
+        )?;
+        write!(
+            f,
+            "focus/break ratio: {}",
+            focus_break_ratio(self.focus_time, self.break_time)
+        )
+    }
+}
+
+/// Focus time per break time with one decimal, "–" without any break time.
+pub fn focus_break_ratio(focus_time: Duration, break_time: Duration) -> String {
+    if break_time.is_zero() {
+        return "–".to_string();
+    }
+
+    format!("{:.1}", focus_time.as_secs_f64() / break_time.as_secs_f64())
+}
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("failed to find a data directory for the statistics")]
+    NoDataDirectory,
+    #[error("the statistics file was written by a newer version of corrodoro (file version {0})")]
+    UnsupportedVersion(u32),
+    #[error("failed to write statistics to {}: {1}", .0.display())]
+    Write(PathBuf, io::Error),
+    #[error("failed to move the unreadable statistics file {} aside: {1}", .0.display())]
+    MoveAside(PathBuf, io::Error),
+}
+
+/// The platform's directory for application data, following the XDG base directory specification on unix.
+fn data_dir() -> Option<PathBuf> {
+    let non_empty_var = |name| env::var_os(name).filter(|value| !value.is_empty());
+
+    if cfg!(windows) {
+        non_empty_var("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty_var("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        non_empty_var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty_var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_break_ratio_needs_break_time() {
+        assert_eq!(focus_break_ratio(Duration::ZERO, Duration::ZERO), "–");
+        assert_eq!(
+            focus_break_ratio(Duration::from_secs(60), Duration::ZERO),
+            "–"
+        );
+        assert_eq!(
+            focus_break_ratio(Duration::ZERO, Duration::from_secs(60)),
+            "0.0"
+        );
+        assert_eq!(
+            focus_break_ratio(Duration::from_secs(100 * 60), Duration::from_secs(20 * 60)),
+            "5.0"
+        );
+        assert_eq!(
+            focus_break_ratio(Duration::from_secs(25 * 60), Duration::from_secs(15 * 60)),
+            "1.7"
+        );
+    }
+
+    /// An empty directory of its own for every test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("corrodoro-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("can create a temporary directory");
+        dir
+    }
+
+    #[test]
+    fn instances_add_to_what_the_others_saved() {
+        let path = test_dir("stats-merge").join(LifetimeStats::FILE_NAME);
+        let mut first = LifetimeStats::load_from(&path).expect("no file yet");
+        let mut second = LifetimeStats::load_from(&path).expect("no file yet");
+
+        first.record_focus_session(Duration::from_secs(25 * 60));
+        first.save_to(&path).expect("can write the file");
+        second.record_focus_session(Duration::from_secs(50 * 60));
+        second.record_break(Duration::from_secs(5 * 60));
+        second.save_to(&path).expect("can write the file");
+        assert_eq!(second.completed_focus_sessions, 2);
+        // nothing new to add
+        first.save_to(&path).expect("can write the file");
+
+        let saved = LifetimeStats::load_from(&path).expect("the file was written");
+        assert_eq!(saved.completed_focus_sessions, 2);
+        assert_eq!(saved.focus_time, Duration::from_secs(75 * 60));
+        assert_eq!(saved.break_time, Duration::from_secs(5 * 60));
+        assert_eq!(saved.focus_sessions_today(), 2);
+        assert_eq!(first.completed_focus_sessions, 2);
+    }
+
+    #[test]
+    fn corrupt_files_are_moved_aside() {
+        let dir = test_dir("stats-corrupt");
+        let path = dir.join(LifetimeStats::FILE_NAME);
+        let corrupt_path = dir.join(LifetimeStats::CORRUPT_FILE_NAME);
+
+        for content in [
+            &b"version=2\nfocus_seconds=\xff\n"[..],
+            b"version=2\nfocus_seconds=a lot\n",
+            b"day=1,2,3\n",
+        ] {
+            fs::write(&path, content).expect("can write the file");
+            let stats = LifetimeStats::load_from(&path).expect("the file can be moved");
+            assert_eq!(stats.completed_focus_sessions, 0);
+            assert!(!path.exists());
+            assert_eq!(fs::read(&corrupt_path).expect("was moved aside"), content);
+        }
+    }
+
+    #[test]
+    fn files_of_newer_versions_are_kept() {
+        let path = test_dir("stats-newer").join(LifetimeStats::FILE_NAME);
+        fs::write(&path, "version=3\n").expect("can write the file");
+
+        assert!(matches!(
+            LifetimeStats::load_from(&path),
+            Err(StatsError::UnsupportedVersion(3))
+        ));
+        let mut stats = LifetimeStats::default();
+        stats.record_break(Duration::from_secs(60));
+        assert!(stats.save_to(&path).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).expect("still there"),
+            "version=3\n"
+        );
+    }
+}
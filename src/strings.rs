@@ -0,0 +1,305 @@
+use clap::ValueEnum;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+/// Language of the user interface. Messages of errors that end the program are always in English.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Lang {
+    /// English
+    En,
+    /// German
+    De,
+}
+
+impl Display for Lang {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lang::En => write!(f, "en"),
+            Lang::De => write!(f, "de"),
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Can only be set once, further calls are ignored.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+/// Returns the strings of the active language, English if none was set.
+pub fn strings() -> &'static Strings {
+    match LANG.get().copied().unwrap_or(Lang::En) {
+        Lang::En => &EN,
+        Lang::De => &DE,
+    }
+}
+
+/// All strings of the user interface.
+/// The first grapheme of titles is highlighted and should be the key that belongs to them.
+pub struct Strings {
+    pub focus: &'static str,
+    pub short_break: &'static str,
+    pub long_break: &'static str,
+
+    pub settings_title: &'static str,
+    pub timer_title: &'static str,
+
+    pub toggle: &'static str,
+    pub adjust: &'static str,
+    pub skip: &'static str,
+    pub end_focus: &'static str,
+    pub reset: &'static str,
+    pub quit: &'static str,
+    pub clients: &'static str,
+    pub sync: &'static str,
+    pub announce: &'static str,
+    pub announcement_prompt: fn(&str) -> String,
+    pub announced: fn(&str) -> String,
+    pub announcement: fn(&str) -> String,
+
+    pub offline: &'static str,
+    pub clients_connected: fn(usize) -> String,
+    pub listening_on_port: fn(u16) -> String,
+    pub connected_to: fn(&str) -> String,
+    pub room: fn(&str) -> String,
+    pub streak: fn(u32) -> String,
+    pub focus_break_ratio: fn(&str, Option<&str>) -> String,
+    pub until_long_break: fn(u32) -> String,
+    pub today: fn(u64) -> String,
+    pub elapsed: fn(&str) -> String,
+    pub done_by: fn(&str) -> String,
+    pub starting_in: fn(u64) -> String,
+    pub paused: &'static str,
+    pub running: &'static str,
+    pub remaining: fn(&str) -> String,
+    pub session_of: fn(u32, u32) -> String,
+    pub keys: fn(&str) -> String,
+    pub reconnecting: fn(u32, u64) -> String,
+    pub link_live: &'static str,
+    pub link_stale: fn(u64) -> String,
+    pub client_list_title: &'static str,
+    pub terminal_too_small: &'static str,
+    pub have: &'static str,
+    pub need: &'static str,
+    pub last_panel_cant_be_hidden: &'static str,
+    pub quit_locked: &'static str,
+    pub still_focusing: &'static str,
+    pub paused_after_check_in: &'static str,
+    pub paused_when_empty: &'static str,
+    pub resumed_with_client: &'static str,
+    pub session_log_write_failed: &'static str,
+    pub snapshot_saved: fn(&str) -> String,
+    pub snapshot_failed: fn(&str) -> String,
+    pub ui_detached: fn(u16) -> String,
+    pub confirm_quit: fn(usize) -> String,
+    pub activity_too_long: fn(&str) -> String,
+    pub desktop_notifications_unavailable: fn(&str, &str) -> String,
+
+    pub client_connected: fn(&str) -> String,
+    pub client_reconnected: fn(&str, &str) -> String,
+    pub client_disconnected: fn(&str) -> String,
+    pub client_disconnected_network_error: fn(&str) -> String,
+    pub client_was_disconnected: fn(&str) -> String,
+    pub client_violated_protocol: fn(&str) -> String,
+    pub host_violated_protocol: &'static str,
+    pub skipped_to: fn(&str) -> String,
+    pub long_break_started: &'static str,
+    pub adjusted: fn(&str, &str) -> String,
+    pub adjusted_permanently: fn(&str, &str, &str, &str) -> String,
+    pub adjust_all: &'static str,
+    pub host: &'static str,
+}
+
+const EN: Strings = Strings {
+    focus: "focus",
+    short_break: "short break",
+    long_break: "long break",
+
+    settings_title: "¹settings",
+    timer_title: "²timer",
+
+    toggle: "toggle",
+    adjust: "adjust",
+    skip: "skip",
+    end_focus: "end focus",
+    reset: "reset",
+    quit: "quit",
+    clients: "clients",
+    sync: "sync",
+    announce: "announce",
+    announcement_prompt: |text| format!("announce: {text}_ (enter sends, esc cancels)"),
+    announced: |text| format!("announced \"{text}\""),
+    announcement: |text| format!("host: {text}"),
+
+    offline: "offline",
+    clients_connected: |n| match n {
+        0 => "no clients connected".to_string(),
+        1 => "one client connected".to_string(),
+        n => format!("{n} clients connected"),
+    },
+    listening_on_port: |port| format!("listening on port {port}"),
+    connected_to: |addr| format!("connected to {addr}"),
+    room: |name| format!("room {name}"),
+    streak: |n| format!("streak: {n}"),
+    focus_break_ratio: |ratio, all_time| match all_time {
+        Some(all_time) => format!("focus/break: {ratio} (all time {all_time})"),
+        None => format!("focus/break: {ratio}"),
+    },
+    until_long_break: |n| format!("{n} until long break"),
+    today: |n| format!("today: {n}"),
+    elapsed: |time| format!("{time} elapsed"),
+    done_by: |time| format!("done by {time}"),
+    starting_in: |secs| format!("starting in {secs}…"),
+    paused: "paused",
+    running: "running",
+    remaining: |time| format!("{time} remaining"),
+    session_of: |session, sessions| format!("session {session} of {sessions}"),
+    keys: |keys| format!("keys: {keys}"),
+    reconnecting: |attempt, secs| format!("reconnecting (attempt {attempt}, retry in {secs}s)"),
+    link_live: "live",
+    link_stale: |secs| format!("stale, no updates for {secs}s"),
+    client_list_title: " press a number to disconnect ",
+    terminal_too_small: "terminal size too small",
+    have: "have",
+    need: "need",
+    last_panel_cant_be_hidden: "the last visible panel can't be hidden",
+    quit_locked: "focus lock: finish the session or press Ctrl-C to quit",
+    still_focusing: "still focusing? press any key",
+    paused_after_check_in: "paused, nobody answered whether they are still focusing",
+    paused_when_empty: "paused, the last client left",
+    resumed_with_client: "resumed, a client connected",
+    session_log_write_failed: "failed to write to the session log",
+    snapshot_saved: |path| format!("snapshot saved to {path}"),
+    snapshot_failed: |err| format!("failed to save snapshot: {err}"),
+    ui_detached: |port| {
+        format!("the session keeps running on port {port}, rejoin it with `corrodoro connect HOSTNAME:{port}`, stop it with Ctrl-C")
+    },
+    confirm_quit: |clients| match clients {
+        1 => "1 client connected, quit again to disconnect it".to_string(),
+        _ => format!("{clients} clients connected, quit again to disconnect them"),
+    },
+    activity_too_long: |max| format!("activities can't be longer than {max}"),
+    desktop_notifications_unavailable: |msg, err| {
+        format!(
+            "{msg} (desktop notifications are unavailable, showing them here from now on: {err})"
+        )
+    },
+
+    client_connected: |client| format!("Client {client} connected"),
+    client_reconnected: |client, previous| format!("Client {client} reconnected, was {previous}"),
+    client_disconnected: |client| format!("Client {client} disconnected"),
+    client_disconnected_network_error: |client| {
+        format!("Client {client} disconnected because of a network error")
+    },
+    client_was_disconnected: |client| format!("Client {client} was disconnected"),
+    client_violated_protocol: |client| {
+        format!("Client {client} was disconnected for sending unexpected messages")
+    },
+    host_violated_protocol: "the host sent an unexpected message, it may run a different version",
+    skipped_to: |activity| format!("skipped to {activity}"),
+    long_break_started: "cycle complete, time for a long break",
+    adjusted: |change, by| format!("{change} by {by}"),
+    adjusted_permanently: |change, activity, duration, by| {
+        format!("{change} by {by}, every {activity} lasts {duration} from now on")
+    },
+    adjust_all: "adjust all",
+    host: "host",
+};
+
+const DE: Strings = Strings {
+    focus: "Fokus",
+    short_break: "kurze Pause",
+    long_break: "lange Pause",
+
+    settings_title: "¹Einstellungen",
+    timer_title: "²Timer",
+
+    toggle: "umschalten",
+    adjust: "anpassen",
+    skip: "überspringen",
+    end_focus: "Fokus beenden",
+    reset: "zurücksetzen",
+    quit: "beenden",
+    clients: "Teilnehmer",
+    sync: "synchronisieren",
+    announce: "durchsagen",
+    announcement_prompt: |text| format!("Durchsage: {text}_ (Enter sendet, Esc bricht ab)"),
+    announced: |text| format!("durchgesagt: \"{text}\""),
+    announcement: |text| format!("Host: {text}"),
+
+    offline: "offline",
+    clients_connected: |n| match n {
+        0 => "keine Teilnehmer verbunden".to_string(),
+        1 => "ein Teilnehmer verbunden".to_string(),
+        n => format!("{n} Teilnehmer verbunden"),
+    },
+    listening_on_port: |port| format!("wartet auf Port {port}"),
+    connected_to: |addr| format!("verbunden mit {addr}"),
+    room: |name| format!("Raum {name}"),
+    streak: |n| format!("Serie: {n}"),
+    focus_break_ratio: |ratio, all_time| match all_time {
+        Some(all_time) => format!("Fokus/Pause: {ratio} (insgesamt {all_time})"),
+        None => format!("Fokus/Pause: {ratio}"),
+    },
+    until_long_break: |n| format!("{n} bis zur langen Pause"),
+    today: |n| format!("heute: {n}"),
+    elapsed: |time| format!("{time} vergangen"),
+    done_by: |time| format!("fertig um {time}"),
+    starting_in: |secs| format!("beginnt in {secs}…"),
+    paused: "pausiert",
+    running: "läuft",
+    remaining: |time| format!("noch {time}"),
+    session_of: |session, sessions| format!("Einheit {session} von {sessions}"),
+    keys: |keys| format!("Tasten: {keys}"),
+    reconnecting: |attempt, secs| format!("verbinde neu (Versuch {attempt}, nächster in {secs}s)"),
+    link_live: "live",
+    link_stale: |secs| format!("veraltet, seit {secs}s keine Updates"),
+    client_list_title: " Nummer drücken zum Trennen ",
+    terminal_too_small: "Terminal zu klein",
+    have: "habe",
+    need: "brauche",
+    last_panel_cant_be_hidden: "das letzte sichtbare Feld kann nicht ausgeblendet werden",
+    quit_locked: "Fokussperre: beende die Sitzung oder drücke Strg-C zum Beenden",
+    still_focusing: "noch konzentriert? drücke eine beliebige Taste",
+    paused_after_check_in: "pausiert, niemand hat bestätigt, noch konzentriert zu sein",
+    paused_when_empty: "pausiert, der letzte Teilnehmer ist gegangen",
+    resumed_with_client: "fortgesetzt, ein Teilnehmer ist verbunden",
+    session_log_write_failed: "Schreiben ins Sitzungsprotokoll fehlgeschlagen",
+    snapshot_saved: |path| format!("Momentaufnahme gespeichert unter {path}"),
+    snapshot_failed: |err| format!("Speichern der Momentaufnahme fehlgeschlagen: {err}"),
+    ui_detached: |port| {
+        format!("die Sitzung läuft auf Port {port} weiter, erneut beitreten mit `corrodoro connect HOSTNAME:{port}`, beenden mit Strg-C")
+    },
+    confirm_quit: |clients| match clients {
+        1 => "1 Teilnehmer verbunden, zum Trennen erneut beenden".to_string(),
+        _ => format!("{clients} Teilnehmer verbunden, zum Trennen aller erneut beenden"),
+    },
+    activity_too_long: |max| format!("Aktivitäten können nicht länger als {max} dauern"),
+    desktop_notifications_unavailable: |msg, err| {
+        format!("{msg} (Desktop-Benachrichtigungen sind nicht verfügbar, sie werden ab jetzt hier angezeigt: {err})")
+    },
+
+    client_connected: |client| format!("Teilnehmer {client} hat sich verbunden"),
+    client_reconnected: |client, previous| {
+        format!("Teilnehmer {client} hat sich erneut verbunden, vorher {previous}")
+    },
+    client_disconnected: |client| format!("Teilnehmer {client} hat die Verbindung getrennt"),
+    client_disconnected_network_error: |client| {
+        format!("Teilnehmer {client} wurde wegen eines Netzwerkfehlers getrennt")
+    },
+    client_was_disconnected: |client| format!("Teilnehmer {client} wurde getrennt"),
+    client_violated_protocol: |client| {
+        format!("Teilnehmer {client} wurde wegen unerwarteter Nachrichten getrennt")
+    },
+    host_violated_protocol:
+        "der Host hat eine unerwartete Nachricht gesendet, vielleicht läuft eine andere Version",
+    skipped_to: |activity| format!("übersprungen zu {activity}"),
+    long_break_started: "Zyklus geschafft, Zeit für eine lange Pause",
+    adjusted: |change, by| format!("{change} von {by}"),
+    adjusted_permanently: |change, activity, duration, by| {
+        format!("{change} von {by}, jede Phase {activity} dauert ab jetzt {duration}")
+    },
+    adjust_all: "alle anpassen",
+    host: "Host",
+};
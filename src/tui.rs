@@ -1,27 +1,161 @@
 use crate::app::NetworkStatus;
-use crate::notification;
-use crate::protocol::{Event, TimerVisuals};
+use crate::notification::{NotifyBackend, QuietHours, SoundBackend};
+use crate::protocol::{self, Event, TimerVisuals};
+use crate::strings::strings;
 use crossterm::event::{
-    Event as CrosstermEvent, EventStream, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind,
+    Event as CrosstermEvent, EventStream, KeyCode, KeyEventKind, KeyModifiers, MouseButton,
+    MouseEventKind,
 };
 use futures::StreamExt;
-use std::io;
-use std::time::Duration;
+use offset_backend::OffsetBackend;
+use panic_hook::TerminalPanicHook;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
-use tui::{backend::CrosstermBackend, Terminal};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    terminal::{TerminalOptions, Viewport},
+    Terminal,
+};
+use zwiesel::ClientId;
 
 mod animation;
+mod keybindings;
+mod offset_backend;
+mod panic_hook;
 mod render;
+mod theme;
 mod widgets;
 
+pub use keybindings::KeyBindings;
+pub use theme::{ColorMode, CounterGlyphs, Theme};
+
+/// Options that change how the tui looks and behaves.
+#[derive(Clone)]
+pub struct Config {
+    /// Share of the screen taken up by the settings panel if both panels are visible.
+    /// Clamped to `SETTINGS_PERCENTAGE_RANGE`.
+    pub settings_percentage: u16,
+    pub show_legend: bool,
+    pub key_bindings: KeyBindings,
+    pub theme: Theme,
+    /// No desktop notifications or sounds during this window, the message is shown as a hint instead.
+    pub quiet_hours: Option<QuietHours>,
+    pub notify_backend: NotifyBackend,
+    pub sound_backend: SoundBackend,
+    /// Capture the mouse to adjust and scrub the timer with it, which prevents selecting text in the terminal.
+    pub mouse: bool,
+    /// Take over the whole screen, otherwise the tui is drawn below the cursor and the scrollback stays intact.
+    pub alternate_screen: bool,
+    /// Report the progress to the terminal, see `report_progress`.
+    pub terminal_progress: bool,
+    /// Draw plain text instead of the clock and symbols, for screen readers and low vision.
+    pub accessible: bool,
+    /// Where snapshots of the tui are saved, see `KeyBindings::snapshot`.
+    pub snapshot_dir: PathBuf,
+}
+
+impl Config {
+    pub const SETTINGS_PERCENTAGE_RANGE: (u16, u16) = (10, 50);
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            settings_percentage: 20,
+            show_legend: true,
+            key_bindings: KeyBindings::default(),
+            theme: Theme::default(),
+            quiet_hours: None,
+            notify_backend: NotifyBackend::default(),
+            sound_backend: SoundBackend::default(),
+            mouse: true,
+            alternate_screen: true,
+            terminal_progress: false,
+            accessible: false,
+            snapshot_dir: PathBuf::from("."),
+        }
+    }
+}
+
+/// What the user asked for, returned by `Tui::read_event`.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum Action {
+    Event(Event),
+    /// Disconnects a client that was shown in the host's client list. Never sent over the network.
+    DisconnectClient(ClientId),
+}
+
 pub struct Tui {
-    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    terminal: Terminal<OffsetBackend>,
     raw_mode_enabled: bool,
     alternate_screen_enabled: bool,
-    event_stream: EventStream,
-    show_settings: bool,
-    show_timer: bool,
+    /// Where the tui is drawn while it is enabled without the alternate screen.
+    inline_area: Option<Rect>,
+    /// Set once the tui was detached, see `detach`.
+    detached: bool,
+    /// Opened on the first read, because opening it needs a terminal.
+    event_stream: Option<EventStream>,
+    panel_layout: PanelLayout,
+    settings_percentage: u16,
+    show_legend: bool,
+    key_bindings: KeyBindings,
+    theme: Theme,
+    quiet_hours: Option<QuietHours>,
+    /// Turned off after the first failure, see `show_notification`.
+    notify_backend: NotifyBackend,
+    sound_backend: SoundBackend,
+    mouse: bool,
+    alternate_screen: bool,
+    terminal_progress: bool,
+    accessible: bool,
+    /// The last progress that was reported to the terminal, to only report changes.
+    reported_progress: Option<TerminalProgress>,
+    show_client_list: bool,
+    /// The announcement that is being typed, see `KeyBindings::announce`.
+    announcement: Option<String>,
+    /// Asks whether the user is still focusing until any input answers it, see `set_check_in_shown`.
+    check_in_shown: bool,
+    /// Last key press, paste or click, also if only the tui handled it.
+    last_input_at: Instant,
+    hint: Option<Hint>,
+    /// Where the clock was last drawn, used to scrub the timer with the mouse.
+    clock_area: Option<Rect>,
+    session_elapsed: Option<Duration>,
+    done_by: Option<u16>,
+    focus_sessions_today: Option<u64>,
+    focus_break_ratio: Option<String>,
+    /// The current step of the idle animation, see `set_idle_dimmed`.
+    idle_dimmed: bool,
+    quit_locked: bool,
     last_display_data: Option<DisplayData>,
+    /// Set when something that is shown besides the display data changed, see `render`.
+    view_changed: bool,
+    snapshot_dir: PathBuf,
+    /// Set to capture the next frame into `snapshot`, see `save_snapshot`.
+    snapshot_requested: bool,
+    snapshot: Option<String>,
+    /// Installed while the tui is enabled.
+    panic_hook: Option<TerminalPanicHook>,
+}
+
+/// A short message that is displayed for a few seconds, e.g. to explain why a key press did nothing.
+struct Hint {
+    text: String,
+    shown_at: Instant,
+}
+
+impl Hint {
+    const DISPLAY_DURATION: Duration = Duration::from_secs(3);
+
+    fn is_expired(&self) -> bool {
+        self.shown_at.elapsed() >= Self::DISPLAY_DURATION
+    }
 }
 
 struct DisplayData {
@@ -29,72 +163,326 @@ struct DisplayData {
     network_status: NetworkStatus,
 }
 
+/// Whether drawing the data would repeat the last frame, see `Tui::render`.
+/// An expired hint still has to be cleared from the screen.
+fn frame_is_unchanged(
+    last_display_data: Option<&DisplayData>,
+    view_changed: bool,
+    hint: Option<&Hint>,
+    timer_visuals: &TimerVisuals,
+    network_status: &NetworkStatus,
+) -> bool {
+    !view_changed
+        && !hint.is_some_and(Hint::is_expired)
+        && last_display_data.is_some_and(|display_data| {
+            display_data.timer_visuals == *timer_visuals
+                && display_data.network_status == *network_status
+        })
+}
+
+/// The states of the OSC 9;4 progress sequence, see https://learn.microsoft.com/en-us/windows/terminal/tutorials/progress-bar-sequences
+#[derive(Clone, Copy, PartialEq)]
+enum TerminalProgress {
+    Hidden,
+    Running(u8),
+    Paused(u8),
+}
+
+impl Display for TerminalProgress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (state, percent) = match self {
+            TerminalProgress::Hidden => (0, 0),
+            TerminalProgress::Running(percent) => (1, *percent),
+            TerminalProgress::Paused(percent) => (4, *percent),
+        };
+        write!(f, "\x1b]9;4;{state};{percent}\x07")
+    }
+}
+
 impl Tui {
-    pub fn new() -> Result<Self, TuiError> {
-        let backend = CrosstermBackend::new(io::stdout());
+    /// Height of the tui without the alternate screen, enough for the clock and its block.
+    const INLINE_HEIGHT: u16 = 16;
+
+    /// Fails if stdout is not a terminal, e.g. because it is piped into a file.
+    pub fn new(config: Config) -> Result<Self, TuiError> {
+        if !io::stdout().is_terminal() {
+            return Err(TuiError::NotATerminal);
+        }
+        let terminal = Terminal::new(OffsetBackend::new(0)).map_err(TuiError::Creation)?;
+        Ok(Self::with_terminal(config, terminal))
+    }
+
+    /// A detached tui with a fixed size that never touches the terminal, to run an app in tests.
+    #[cfg(test)]
+    pub fn headless(config: Config) -> Self {
+        let options = TerminalOptions {
+            viewport: Viewport::fixed(Rect::new(0, 0, 80, 24)),
+        };
+        let terminal = Terminal::with_options(OffsetBackend::new(0), options)
+            .expect("creating a terminal with a fixed viewport doesn't query it");
+        let mut tui = Self::with_terminal(config, terminal);
+        tui.detached = true;
+        tui
+    }
+
+    fn with_terminal(config: Config, terminal: Terminal<OffsetBackend>) -> Self {
+        let (min_settings_percentage, max_settings_percentage) = Config::SETTINGS_PERCENTAGE_RANGE;
 
-        Ok(Tui {
-            terminal: Terminal::new(backend).map_err(TuiError::Creation)?,
+        Tui {
+            terminal,
             alternate_screen_enabled: false,
+            inline_area: None,
             raw_mode_enabled: false,
-            event_stream: EventStream::new(),
-            show_settings: true,
-            show_timer: true,
+            detached: false,
+            event_stream: None,
+            panel_layout: PanelLayout::Both,
+            settings_percentage: config
+                .settings_percentage
+                .clamp(min_settings_percentage, max_settings_percentage),
+            show_legend: config.show_legend,
+            key_bindings: config.key_bindings,
+            theme: config.theme,
+            quiet_hours: config.quiet_hours,
+            notify_backend: config.notify_backend,
+            sound_backend: config.sound_backend,
+            mouse: config.mouse,
+            alternate_screen: config.alternate_screen,
+            terminal_progress: config.terminal_progress,
+            accessible: config.accessible,
+            reported_progress: None,
+            show_client_list: false,
+            announcement: None,
+            check_in_shown: false,
+            last_input_at: Instant::now(),
+            hint: None,
+            clock_area: None,
+            session_elapsed: None,
+            done_by: None,
+            focus_sessions_today: None,
+            focus_break_ratio: None,
+            idle_dimmed: false,
+            quit_locked: false,
             last_display_data: None,
-        })
+            view_changed: true,
+            snapshot_dir: config.snapshot_dir,
+            snapshot_requested: false,
+            snapshot: None,
+            panic_hook: None,
+        }
     }
 
     /// Has to be explicitly disabled, because disabling can cause errors that have to be catched.
     /// Is not disabled by dropping.
     pub fn enable(&mut self) -> Result<(), TuiError> {
+        self.install_panic_hook();
+        // the screen was cleared or moved
+        self.view_changed = true;
+
         crossterm::terminal::enable_raw_mode().map_err(TuiError::RawModeToggle)?;
         self.raw_mode_enabled = true;
 
-        crossterm::execute!(
+        if self.alternate_screen {
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::terminal::EnterAlternateScreen,
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+                crossterm::cursor::Hide,
+            )
+            .map_err(TuiError::AlternateScreenToggle)?;
+            self.alternate_screen_enabled = true;
+        } else {
+            let area = reserve_inline_area(Self::INLINE_HEIGHT)
+                .map_err(TuiError::AlternateScreenToggle)?;
+            // the viewport is fixed, otherwise the terminal would resize it to the whole screen
+            self.terminal = Terminal::with_options(
+                OffsetBackend::new(area.y),
+                TerminalOptions {
+                    viewport: Viewport::fixed(Rect { y: 0, ..area }),
+                },
+            )
+            .map_err(TuiError::Creation)?;
+            crossterm::execute!(self.terminal.backend_mut(), crossterm::cursor::Hide)
+                .map_err(TuiError::AlternateScreenToggle)?;
+            self.inline_area = Some(area);
+        }
+        if self.mouse {
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::event::EnableMouseCapture
+            )
+            .map_err(TuiError::AlternateScreenToggle)?;
+        }
+        // not supported by every terminal, pasted text is then read as key presses like before
+        let _ = crossterm::execute!(
             self.terminal.backend_mut(),
-            crossterm::terminal::EnterAlternateScreen,
-            crossterm::event::EnableMouseCapture,
-            crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
-        )
-        .map_err(TuiError::AlternateScreenToggle)?;
-        self.alternate_screen_enabled = true;
+            crossterm::event::EnableBracketedPaste
+        );
 
         Ok(())
     }
 
     pub fn disable(&mut self) -> Result<(), TuiError> {
+        // first, so that the hook is gone even if restoring the terminal fails below
+        self.uninstall_panic_hook();
+        if self.reported_progress.take().is_some() {
+            let _ = write!(self.terminal.backend_mut(), "{}", TerminalProgress::Hidden)
+                .and_then(|_| self.terminal.backend_mut().flush());
+        }
+        if self.alternate_screen_enabled || self.inline_area.is_some() {
+            let _ = crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::event::DisableBracketedPaste
+            );
+        }
+        if self.mouse && (self.alternate_screen_enabled || self.inline_area.is_some()) {
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::event::DisableMouseCapture
+            )
+            .map_err(TuiError::AlternateScreenToggle)?;
+        }
         if self.alternate_screen_enabled {
             crossterm::execute!(
                 self.terminal.backend_mut(),
                 crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+                crossterm::cursor::Show,
                 crossterm::terminal::LeaveAlternateScreen,
-                crossterm::event::DisableMouseCapture,
+            )
+            .map_err(TuiError::AlternateScreenToggle)?;
+            self.alternate_screen_enabled = false;
+        }
+        // only the lines of the tui are cleared, the shell continues where the tui started
+        if let Some(area) = self.inline_area.take() {
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::cursor::MoveTo(0, area.y),
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown),
+                crossterm::cursor::Show,
             )
             .map_err(TuiError::AlternateScreenToggle)?;
         }
         if self.raw_mode_enabled {
             crossterm::terminal::disable_raw_mode().map_err(TuiError::RawModeToggle)?;
+            self.raw_mode_enabled = false;
         }
 
         Ok(())
     }
 
+    /// Gives the terminal back for good while the app keeps running.
+    /// Afterwards, nothing is rendered, no notifications are shown, and no events are read.
+    pub fn detach(&mut self) -> Result<(), TuiError> {
+        self.disable()?;
+        self.detached = true;
+
+        Ok(())
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    /// See `TerminalPanicHook`.
+    fn install_panic_hook(&mut self) {
+        if self.panic_hook.is_some() {
+            return;
+        }
+
+        let alternate_screen = self.alternate_screen;
+        self.panic_hook = Some(TerminalPanicHook::install(move || {
+            restore_terminal(alternate_screen)
+        }));
+    }
+
+    fn uninstall_panic_hook(&mut self) {
+        if let Some(panic_hook) = self.panic_hook.take() {
+            panic_hook.uninstall();
+        }
+    }
+
+    /// Skips drawing if nothing visible changed since the last frame. Changes to the tui itself,
+    /// like toggled panels or a resized terminal, are drawn right away by `rerender`.
     pub fn render(
         &mut self,
         timer_visuals: &TimerVisuals,
         network_status: &NetworkStatus,
     ) -> Result<(), TuiError> {
-        self.terminal
-            .draw(|f| {
-                render::render_ui(
-                    f,
-                    timer_visuals,
-                    network_status,
-                    self.show_settings,
-                    self.show_timer,
-                );
+        if self.detached {
+            return Ok(());
+        }
+        if frame_is_unchanged(
+            self.last_display_data.as_ref(),
+            self.view_changed,
+            self.hint.as_ref(),
+            timer_visuals,
+            network_status,
+        ) {
+            return Ok(());
+        }
+
+        self.draw(timer_visuals, network_status)
+    }
+
+    fn draw(
+        &mut self,
+        timer_visuals: &TimerVisuals,
+        network_status: &NetworkStatus,
+    ) -> Result<(), TuiError> {
+        if self.hint.as_ref().is_some_and(Hint::is_expired) {
+            self.hint = None;
+        }
+        let (timer_legend, settings_legend) = match self.show_legend {
+            true => (
+                self.key_bindings.timer_legend(self.accessible),
+                match network_status {
+                    NetworkStatus::Server { .. } => {
+                        self.key_bindings.settings_legend_host(self.accessible)
+                    }
+                    NetworkStatus::Client { .. } | NetworkStatus::Reconnecting { .. } => {
+                        self.key_bindings.settings_legend_client(self.accessible)
+                    }
+                    NetworkStatus::Offline => vec![],
+                },
+            ),
+            false => (vec![], vec![]),
+        };
+        let announcement_prompt = self
+            .announcement
+            .as_deref()
+            .map(strings().announcement_prompt);
+        let check_in = self.check_in_shown.then_some(strings().still_focusing);
+        let view = render::View {
+            panel_layout: self.panel_layout,
+            settings_percentage: self.settings_percentage,
+            show_client_list: self.show_client_list,
+            hint: announcement_prompt
+                .as_deref()
+                .or(check_in)
+                .or(self.hint.as_ref().map(|hint| hint.text.as_str())),
+            session_elapsed: self.session_elapsed,
+            done_by: self.done_by,
+            focus_sessions_today: self.focus_sessions_today,
+            focus_break_ratio: self.focus_break_ratio.as_deref(),
+            idle_dimmed: self.idle_dimmed,
+            theme: &self.theme,
+            timer_legend: &timer_legend,
+            settings_legend: &settings_legend,
+        };
+
+        let mut clock_area = None;
+        let completed_frame = self
+            .terminal
+            .draw(|f| match self.accessible {
+                true => render::render_accessible(f, timer_visuals, network_status, &view),
+                false => clock_area = render::render_ui(f, timer_visuals, network_status, &view),
             })
             .map_err(TuiError::Rendering)?;
+        if mem::take(&mut self.snapshot_requested) {
+            self.snapshot = Some(buffer_text(completed_frame.buffer));
+        }
+        self.clock_area = clock_area;
+        self.view_changed = false;
+        self.report_progress(timer_visuals);
 
         self.last_display_data = Some(DisplayData {
             timer_visuals: *timer_visuals,
@@ -104,70 +492,531 @@ impl Tui {
         Ok(())
     }
 
-    pub fn show_notification(&self, msg: &str, audio: bool) {
-        // ignore errors for now, perhaps add a log message in the tui in the future
-        let _ = notification::show_desktop_notification("", msg);
+    /// Writes the progress as OSC 9;4 sequence, which some terminals show in their tab or taskbar.
+    /// Failures are ignored, the progress is only decoration.
+    fn report_progress(&mut self, timer_visuals: &TimerVisuals) {
+        if !self.terminal_progress {
+            return;
+        }
+        let percent = (timer_visuals.progress_percentage.clamp(0.0, 1.0) * 100.0).round() as u8;
+        let progress = match timer_visuals.timer_is_paused {
+            true => TerminalProgress::Paused(percent),
+            false => TerminalProgress::Running(percent),
+        };
+        if self.reported_progress == Some(progress) {
+            return;
+        }
+
+        let backend = self.terminal.backend_mut();
+        if write!(backend, "{progress}")
+            .and_then(|_| backend.flush())
+            .is_ok()
+        {
+            self.reported_progress = Some(progress);
+        }
+    }
+
+    /// Whether a hint is shown, it disappears on the first render after it expired.
+    pub fn has_hint(&self) -> bool {
+        self.hint.is_some()
+    }
+
+    pub fn show_hint(&mut self, text: &str) {
+        self.hint = Some(Hint {
+            text: text.to_string(),
+            shown_at: Instant::now(),
+        });
+        self.view_changed = true;
+    }
+
+    /// Shown in the settings panel from the next render on. `None` hides it.
+    pub fn set_session_elapsed(&mut self, elapsed: Option<Duration>) {
+        self.view_changed |= self.session_elapsed != elapsed;
+        self.session_elapsed = elapsed;
+    }
+
+    /// Local time of day in minutes since midnight, shown in the settings panel from the next render on. `None` hides it.
+    pub fn set_done_by(&mut self, minute_of_day: Option<u16>) {
+        self.view_changed |= self.done_by != minute_of_day;
+        self.done_by = minute_of_day;
+    }
+
+    /// Shows the question whether the user is still focusing until it is hidden or answered by any input.
+    pub fn set_check_in_shown(&mut self, shown: bool) {
+        self.view_changed |= self.check_in_shown != shown;
+        self.check_in_shown = shown;
+    }
+
+    /// Input that the tui handled on its own counts too, e.g. toggled panels, see `read_event`.
+    pub fn last_input_at(&self) -> Instant {
+        self.last_input_at
+    }
+
+    /// While locked, the quit key and Esc only show a hint. Ctrl-C and Ctrl-D always quit.
+    pub fn set_quit_locked(&mut self, quit_locked: bool) {
+        self.quit_locked = quit_locked;
+    }
+
+    /// Shown in the corner of the timer panel from the next render on. `None` hides it.
+    pub fn set_focus_sessions_today(&mut self, focus_sessions_today: Option<u64>) {
+        self.view_changed |= self.focus_sessions_today != focus_sessions_today;
+        self.focus_sessions_today = focus_sessions_today;
+    }
+
+    /// Shown in the settings panel from the next render on, see `strings().focus_break_ratio`. `None` hides it.
+    pub fn set_focus_break_ratio(&mut self, focus_break_ratio: Option<String>) {
+        self.view_changed |= self.focus_break_ratio != focus_break_ratio;
+        self.focus_break_ratio = focus_break_ratio;
+    }
+
+    /// Dims the border of the timer from the next render on. Toggled by the app while the timer is paused,
+    /// so that the paused clock pulses slowly instead of looking frozen.
+    pub fn set_idle_dimmed(&mut self, idle_dimmed: bool) {
+        self.view_changed |= self.idle_dimmed != idle_dimmed;
+        self.idle_dimmed = idle_dimmed;
+    }
+
+    /// A failing desktop notification is not fatal, but the first failure is shown as a hint so that it doesn't go unnoticed.
+    pub fn show_notification(&mut self, msg: &str, audio: bool) {
+        if self.detached {
+            return;
+        }
+        if self
+            .quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.contain_now())
+        {
+            self.show_hint(msg);
+            return;
+        }
+
+        match self.notify_backend.notify("", msg) {
+            Ok(()) if self.notify_backend.shows_message() => {}
+            Ok(()) => self.show_hint(msg),
+            Err(err) => {
+                tracing::warn!(%err, "notifications failed, showing hints instead");
+                // without a notification service every further attempt fails the same way, possibly after a timeout
+                self.notify_backend = NotifyBackend::None;
+                self.show_hint(&(strings().desktop_notifications_unavailable)(
+                    msg,
+                    &err.to_string(),
+                ));
+            }
+        }
         if audio {
-            notification::play_notification_sound();
+            self.sound_backend.play();
         }
     }
 
-    pub async fn read_event(&mut self) -> Result<Event, TuiError> {
+    /// A quiet sound without a message, skipped during quiet hours like notifications.
+    pub fn play_chime(&self) {
+        if self.detached
+            || self
+                .quiet_hours
+                .is_some_and(|quiet_hours| quiet_hours.contain_now())
+        {
+            return;
+        }
+
+        self.sound_backend.play_quietly();
+    }
+
+    /// Writes the last frame as plain text to a new file in the snapshot directory and tells the user where.
+    fn save_snapshot(&mut self) -> Result<(), TuiError> {
+        self.snapshot_requested = true;
+        self.rerender()?;
+        // nothing has been rendered yet
+        let Some(snapshot) = self.snapshot.take() else {
+            return Ok(());
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self.snapshot_dir.join(format!("corrodoro-{timestamp}.txt"));
+        match fs::write(&path, snapshot) {
+            Ok(()) => self.show_hint(&(strings().snapshot_saved)(&path.display().to_string())),
+            Err(err) => self.show_hint(&(strings().snapshot_failed)(&err.to_string())),
+        }
+
+        self.rerender()
+    }
+
+    /// Renders the last displayed data again, e.g. after the terminal was resized.
+    fn rerender(&mut self) -> Result<(), TuiError> {
+        if self.detached {
+            return Ok(());
+        }
+        if let Some(display_data) = self.last_display_data.take() {
+            self.draw(&display_data.timer_visuals, &display_data.network_status)?;
+            self.last_display_data = Some(display_data);
+        }
+
+        Ok(())
+    }
+
+    pub async fn read_event(&mut self) -> Result<Action, TuiError> {
+        if self.detached {
+            return std::future::pending().await;
+        }
         loop {
             let crossterm_event = self.read_crossterm_event().await?;
+            if is_input(&crossterm_event) {
+                self.last_input_at = Instant::now();
+                if mem::take(&mut self.check_in_shown) {
+                    self.rerender()?;
+                }
+            }
 
-            // just slapped in real quick, could do this nicer
-            self.handle_crossterm_event(&crossterm_event)?;
-
-            if let Ok(event) = Event::try_from(crossterm_event) {
-                return Ok(event);
+            if let Some(action) = self.handle_crossterm_event(crossterm_event)? {
+                return Ok(action);
             }
         }
     }
 
+    /// Reading is retried if it was interrupted by a signal, other errors are fatal.
     async fn read_crossterm_event(&mut self) -> Result<CrosstermEvent, TuiError> {
-        let event = self.event_stream.next().await;
-        let event = event
-            .ok_or(TuiError::EventStreamClosed)?
-            .map_err(TuiError::ReadInputEvent)?;
-
-        Ok(event)
+        let event_stream = self.event_stream.get_or_insert_with(EventStream::new);
+        loop {
+            match event_stream.next().await {
+                Some(Ok(event)) => return Ok(event),
+                Some(Err(err)) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Some(Err(err)) => return Err(TuiError::ReadInputEvent(err)),
+                None => return Err(TuiError::EventStreamClosed),
+            }
+        }
     }
 
-    fn handle_crossterm_event(&mut self, event: &CrosstermEvent) -> Result<(), TuiError> {
+    /// Handles events that only affect the tui itself and converts the remaining ones into actions for the app.
+    fn handle_crossterm_event(
+        &mut self,
+        event: CrosstermEvent,
+    ) -> Result<Option<Action>, TuiError> {
         match event {
-            CrosstermEvent::Key(key_event) if key_event.kind != KeyEventKind::Release => {
+            // key combinations with Ctrl never toggle panels or the client list, Ctrl-C quits in every view
+            CrosstermEvent::Key(key_event)
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) => {}
+            CrosstermEvent::Paste(text) if self.announcement.is_some() => {
+                self.type_announcement(&text);
+                self.rerender()?;
+                return Ok(None);
+            }
+            CrosstermEvent::Key(key_event)
+                if key_event.kind != KeyEventKind::Release && self.announcement.is_some() =>
+            {
+                let event = self.edit_announcement(key_event.code);
+                self.rerender()?;
+                return Ok(event.map(Action::Event));
+            }
+            // a pasted "q" must not quit, pasted text is never a command
+            CrosstermEvent::Paste(_) => return Ok(None),
+            // also works while the client list is shown, so that it can be captured
+            CrosstermEvent::Key(key_event)
+                if key_event.kind != KeyEventKind::Release
+                    && key_event.code == self.key_bindings.snapshot =>
+            {
+                self.save_snapshot()?;
+                return Ok(None);
+            }
+            CrosstermEvent::Key(key_event)
+                if key_event.kind != KeyEventKind::Release && self.show_client_list =>
+            {
                 match key_event.code {
-                    KeyCode::Char('1') => self.toggle_settings(),
-                    KeyCode::Char('2') => self.toggle_timer(),
+                    KeyCode::Char(c @ '1'..='9') => {
+                        let index = c.to_digit(10).unwrap_or_default() as usize - 1;
+                        return Ok(self.listed_client(index).map(Action::DisconnectClient));
+                    }
+                    code if code == self.key_bindings.client_list || code == KeyCode::Esc => {
+                        self.show_client_list = false;
+                        self.rerender()?;
+                        return Ok(None);
+                    }
                     _ => {}
                 }
             }
-            CrosstermEvent::Resize(_, _) => {
-                if let Some(display_data) = self.last_display_data.take() {
-                    self.render(&display_data.timer_visuals, &display_data.network_status)?;
-                    self.last_display_data = Some(display_data);
+            CrosstermEvent::Key(key_event)
+                if key_event.kind != KeyEventKind::Release
+                    && key_event.code == self.key_bindings.client_list
+                    && self.is_hosting() =>
+            {
+                self.show_client_list = true;
+                self.rerender()?;
+                return Ok(None);
+            }
+            CrosstermEvent::Key(key_event)
+                if key_event.kind != KeyEventKind::Release
+                    && key_event.code == self.key_bindings.announce
+                    && self.is_hosting() =>
+            {
+                self.announcement = Some(String::new());
+                self.rerender()?;
+                return Ok(None);
+            }
+            CrosstermEvent::Key(key_event)
+                if key_event.kind != KeyEventKind::Release
+                    && self.quit_locked
+                    && (key_event.code == self.key_bindings.quit
+                        || key_event.code == KeyCode::Esc) =>
+            {
+                self.show_hint(strings().quit_locked);
+                self.rerender()?;
+                return Ok(None);
+            }
+            CrosstermEvent::Key(key_event) if key_event.kind != KeyEventKind::Release => {
+                let panel_layout = match key_event.code {
+                    KeyCode::Char('1') => self.panel_layout.toggle_settings(),
+                    KeyCode::Char('2') => self.panel_layout.toggle_timer(),
+                    KeyCode::Tab => Some(self.panel_layout.next()),
+                    KeyCode::BackTab => Some(self.panel_layout.previous()),
+                    _ => return Ok(self.convert_event(event).map(Action::Event)),
+                };
+
+                match panel_layout {
+                    Some(panel_layout) => self.panel_layout = panel_layout,
+                    None => self.show_hint(strings().last_panel_cant_be_hidden),
                 }
+                self.rerender()?;
+                return Ok(None);
             }
+            CrosstermEvent::Resize(_, _) => self.rerender()?,
             _ => {}
         }
 
-        Ok(())
+        Ok(self.convert_event(event).map(Action::Event))
+    }
+
+    /// The client at `index` of the client list as it was last drawn, so that a client that
+    /// connected or left in the meantime doesn't shift the numbers under the user's fingers.
+    fn listed_client(&self, index: usize) -> Option<ClientId> {
+        match &self.last_display_data.as_ref()?.network_status {
+            NetworkStatus::Server {
+                connected_clients, ..
+            } => connected_clients.get(index).copied(),
+            _ => None,
+        }
+    }
+
+    /// Enter sends the announcement, Esc discards it.
+    fn edit_announcement(&mut self, code: KeyCode) -> Option<Event> {
+        match code {
+            KeyCode::Enter => {
+                let text = protocol::sanitize_announcement(&self.announcement.take()?);
+                return (!text.is_empty()).then_some(Event::Announce(text));
+            }
+            KeyCode::Esc => self.announcement = None,
+            KeyCode::Backspace => {
+                self.announcement.as_mut()?.pop();
+            }
+            KeyCode::Char(c) => self.type_announcement(&c.to_string()),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn type_announcement(&mut self, text: &str) {
+        if let Some(announcement) = &mut self.announcement {
+            let typed = announcement.chars().count();
+            announcement.extend(
+                text.chars()
+                    .filter(|c| !c.is_control())
+                    .take(protocol::MAX_ANNOUNCEMENT_LENGTH.saturating_sub(typed)),
+            );
+        }
+    }
+
+    // TODO: accept uppercase chars too
+    fn convert_event(&self, event: CrosstermEvent) -> Option<Event> {
+        match event {
+            CrosstermEvent::Key(key_event)
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                match key_event.code {
+                    // Ctrl-D out of habit from shells, Ctrl-Z is ignored because the timer can't be suspended
+                    KeyCode::Char('c') | KeyCode::Char('d') => Some(Event::Quit),
+                    _ => None,
+                }
+            }
+            CrosstermEvent::Key(key_event) if key_event.kind != KeyEventKind::Release => {
+                match key_event.code {
+                    KeyCode::Esc => Some(Event::Quit),
+                    code if key_event.modifiers.contains(KeyModifiers::SHIFT) => {
+                        self.key_bindings.shifted_event(code)
+                    }
+                    code => self.key_bindings.event(code),
+                }
+            }
+            // not captured in the first place, ignored as well so that this doesn't depend on the terminal
+            CrosstermEvent::Mouse(_) if !self.mouse => None,
+            CrosstermEvent::Mouse(mouse_event) => match mouse_event.kind {
+                MouseEventKind::ScrollUp => {
+                    Some(Event::ExtendActivity(KeyBindings::ADJUSTMENT_STEP))
+                }
+                MouseEventKind::ScrollDown => {
+                    Some(Event::ReduceActivity(KeyBindings::ADJUSTMENT_STEP))
+                }
+                MouseEventKind::Down(MouseButton::Left)
+                | MouseEventKind::Drag(MouseButton::Left) => {
+                    self.scrub(mouse_event.column, mouse_event.row)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Maps a position on the clock from left to right to the progress of the activity.
+    fn scrub(&self, column: u16, row: u16) -> Option<Event> {
+        // in narrower areas, a single column would skip a large part of the activity
+        const MIN_WIDTH: u16 = 10;
+
+        let area = self.clock_area.filter(|area| area.width >= MIN_WIDTH)?;
+        let is_inside = (area.left()..area.right()).contains(&column)
+            && (area.top()..area.bottom()).contains(&row);
+        if !is_inside {
+            return None;
+        }
+
+        let fraction = f64::from(column - area.left()) / f64::from(area.width - 1);
+        Some(Event::SetProgress(fraction))
+    }
+
+    fn is_hosting(&self) -> bool {
+        matches!(
+            self.last_display_data,
+            Some(DisplayData {
+                network_status: NetworkStatus::Server { .. },
+                ..
+            })
+        )
+    }
+}
+
+/// Which panels are visible. Hiding all panels is deliberately not representable.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+enum PanelLayout {
+    Both,
+    SettingsOnly,
+    TimerOnly,
+}
+
+impl PanelLayout {
+    fn shows_settings(&self) -> bool {
+        matches!(self, PanelLayout::Both | PanelLayout::SettingsOnly)
+    }
+
+    fn shows_timer(&self) -> bool {
+        matches!(self, PanelLayout::Both | PanelLayout::TimerOnly)
+    }
+
+    fn next(self) -> Self {
+        match self {
+            PanelLayout::Both => PanelLayout::SettingsOnly,
+            PanelLayout::SettingsOnly => PanelLayout::TimerOnly,
+            PanelLayout::TimerOnly => PanelLayout::Both,
+        }
     }
 
-    fn toggle_settings(&mut self) {
-        self.show_settings = !self.show_settings;
+    fn previous(self) -> Self {
+        match self {
+            PanelLayout::Both => PanelLayout::TimerOnly,
+            PanelLayout::SettingsOnly => PanelLayout::Both,
+            PanelLayout::TimerOnly => PanelLayout::SettingsOnly,
+        }
     }
 
-    fn toggle_timer(&mut self) {
-        self.show_timer = !self.show_timer;
+    /// Returns `None` if the settings panel is the only visible panel.
+    fn toggle_settings(self) -> Option<Self> {
+        match self {
+            PanelLayout::Both => Some(PanelLayout::TimerOnly),
+            PanelLayout::TimerOnly => Some(PanelLayout::Both),
+            PanelLayout::SettingsOnly => None,
+        }
+    }
+
+    /// Returns `None` if the timer panel is the only visible panel.
+    fn toggle_timer(self) -> Option<Self> {
+        match self {
+            PanelLayout::Both => Some(PanelLayout::SettingsOnly),
+            PanelLayout::SettingsOnly => Some(PanelLayout::Both),
+            PanelLayout::TimerOnly => None,
+        }
     }
 }
 
+/// The symbols of every cell, line by line, without styles.
+fn buffer_text(buffer: &Buffer) -> String {
+    let area = buffer.area;
+    let mut text =
+        String::from("corrodoro frame snapshot, colors and text styles are not included\n\n");
+
+    for y in area.top()..area.bottom() {
+        let line: String = (area.left()..area.right())
+            .map(|x| buffer.get(x, y).symbol.as_str())
+            .collect();
+        text += line.trim_end();
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Whether the user did something, as opposed to e.g. moving the mouse or resizing the terminal.
+fn is_input(event: &CrosstermEvent) -> bool {
+    match event {
+        CrosstermEvent::Key(key_event) => key_event.kind != KeyEventKind::Release,
+        CrosstermEvent::Mouse(mouse_event) => !matches!(
+            mouse_event.kind,
+            MouseEventKind::Moved | MouseEventKind::Up(_)
+        ),
+        CrosstermEvent::Paste(_) => true,
+        _ => false,
+    }
+}
+
+/// Best effort attempt to leave the terminal in a usable state, errors are ignored.
+/// Used when the `Tui` can't be disabled regularly, e.g. while panicking.
+fn restore_terminal(alternate_screen: bool) {
+    let _ = crossterm::terminal::disable_raw_mode();
+    if alternate_screen {
+        let _ = crossterm::execute!(io::stdout(), crossterm::terminal::LeaveAlternateScreen);
+    }
+    let _ = crossterm::execute!(
+        io::stdout(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste,
+        crossterm::cursor::Show,
+    );
+}
+
+/// Makes room for `height` lines below the cursor, scrolling the terminal if the cursor is close to the bottom,
+/// and clears them. Expects the cursor at the start of a line.
+fn reserve_inline_area(height: u16) -> io::Result<Rect> {
+    let (width, screen_height) = crossterm::terminal::size()?;
+    let height = height.min(screen_height);
+
+    crossterm::execute!(
+        io::stdout(),
+        crossterm::style::Print("\n".repeat(usize::from(height.saturating_sub(1)))),
+    )?;
+    let (_, cursor_y) = crossterm::cursor::position()?;
+    let top = (cursor_y + 1).saturating_sub(height);
+    crossterm::execute!(
+        io::stdout(),
+        crossterm::cursor::MoveTo(0, top),
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown),
+    )?;
+
+    Ok(Rect::new(0, top, width, height))
+}
+
 #[derive(Debug, Error)]
 pub enum TuiError {
     #[error("failed to initialize terminal ui: {0}")]
     Creation(io::Error),
+    #[error("stdout is not a terminal, the timer needs one to display its ui (use `offline --plan` for plain text output)")]
+    NotATerminal,
     #[error("failed to toggle terminal raw mode: {0}")]
     RawModeToggle(io::Error),
     #[error("failed to toggle alternate terminal screen: {0}")]
@@ -180,41 +1029,182 @@ pub enum TuiError {
     EventStreamClosed,
 }
 
-pub struct EventConversionUndefinedError;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::{Settings, State};
 
-impl TryFrom<CrosstermEvent> for Event {
-    type Error = EventConversionUndefinedError;
+    fn drawn(state: &State) -> DisplayData {
+        DisplayData {
+            timer_visuals: TimerVisuals::from(state),
+            network_status: NetworkStatus::Offline,
+        }
+    }
 
-    // TODO: accept uppercase chars too
-    fn try_from(event: CrosstermEvent) -> Result<Self, Self::Error> {
-        match event {
-            CrosstermEvent::Key(key_event)
-                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
-            {
-                match key_event.code {
-                    KeyCode::Char('c') => Some(Event::Quit),
-                    _ => None,
+    #[test]
+    fn ticks_of_a_paused_timer_are_not_drawn() {
+        let mut state = State::new(Settings::default());
+        let last = drawn(&state);
+
+        // the app doesn't add progress to a paused timer, its ticks produce the same frame
+        let visuals = TimerVisuals::from(&state);
+        assert!(frame_is_unchanged(
+            Some(&last),
+            false,
+            None,
+            &visuals,
+            &NetworkStatus::Offline
+        ));
+
+        state.toggle_timer();
+        let visuals = TimerVisuals::from(&state);
+        assert!(!frame_is_unchanged(
+            Some(&last),
+            false,
+            None,
+            &visuals,
+            &NetworkStatus::Offline
+        ));
+    }
+
+    #[test]
+    fn changes_besides_the_timer_are_drawn() {
+        let state = State::new(Settings::default());
+        let last = drawn(&state);
+        let visuals = TimerVisuals::from(&state);
+
+        assert!(!frame_is_unchanged(
+            None,
+            false,
+            None,
+            &visuals,
+            &NetworkStatus::Offline
+        ));
+        assert!(!frame_is_unchanged(
+            Some(&last),
+            true,
+            None,
+            &visuals,
+            &NetworkStatus::Offline
+        ));
+
+        let fresh_hint = Hint {
+            text: "hint".to_string(),
+            shown_at: Instant::now(),
+        };
+        assert!(frame_is_unchanged(
+            Some(&last),
+            false,
+            Some(&fresh_hint),
+            &visuals,
+            &NetworkStatus::Offline
+        ));
+        let expired_hint = Hint {
+            shown_at: Instant::now() - Hint::DISPLAY_DURATION,
+            ..fresh_hint
+        };
+        assert!(!frame_is_unchanged(
+            Some(&last),
+            false,
+            Some(&expired_hint),
+            &visuals,
+            &NetworkStatus::Offline
+        ));
+    }
+
+    #[test]
+    fn the_last_visible_panel_cant_be_hidden() {
+        let all = [
+            PanelLayout::Both,
+            PanelLayout::SettingsOnly,
+            PanelLayout::TimerOnly,
+        ];
+        for layout in all {
+            // fails to compile when a variant is added without adding it to `all`
+            match layout {
+                PanelLayout::Both | PanelLayout::SettingsOnly | PanelLayout::TimerOnly => {}
+            }
+            assert!(layout.shows_settings() || layout.shows_timer());
+
+            match layout.toggle_settings() {
+                None => assert_eq!(layout, PanelLayout::SettingsOnly),
+                Some(toggled) => {
+                    assert_ne!(toggled.shows_settings(), layout.shows_settings());
+                    assert_eq!(toggled.shows_timer(), layout.shows_timer());
+                    assert_eq!(toggled.toggle_settings(), Some(layout));
                 }
             }
-            CrosstermEvent::Key(key_event) if key_event.kind != KeyEventKind::Release => {
-                match key_event.code {
-                    KeyCode::Char('q') => Some(Event::Quit),
-                    KeyCode::Char('r') => Some(Event::ResetTimer),
-                    KeyCode::Char('s') => Some(Event::SkipActivity),
-                    KeyCode::Char(' ') => Some(Event::ToggleTimer),
-                    KeyCode::Up => Some(Event::ExtendActivity(Duration::from_secs(60))),
-                    KeyCode::Down => Some(Event::ReduceActivity(Duration::from_secs(60))),
-                    KeyCode::Esc => Some(Event::Quit),
-                    _ => None,
+            match layout.toggle_timer() {
+                None => assert_eq!(layout, PanelLayout::TimerOnly),
+                Some(toggled) => {
+                    assert_ne!(toggled.shows_timer(), layout.shows_timer());
+                    assert_eq!(toggled.shows_settings(), layout.shows_settings());
+                    assert_eq!(toggled.toggle_timer(), Some(layout));
                 }
             }
-            CrosstermEvent::Mouse(mouse_event) => match mouse_event.kind {
-                MouseEventKind::ScrollUp => Some(Event::ExtendActivity(Duration::from_secs(60))),
-                MouseEventKind::ScrollDown => Some(Event::ReduceActivity(Duration::from_secs(60))),
-                _ => None,
-            },
-            _ => None,
+
+            for cycled in [layout.next(), layout.previous()] {
+                assert!(cycled.shows_settings() || cycled.shows_timer());
+                assert_ne!(cycled, layout);
+            }
+            assert_eq!(layout.next().previous(), layout);
+            assert_eq!(layout.next().next().next(), layout);
         }
-        .ok_or(EventConversionUndefinedError)
+    }
+
+    #[test]
+    fn panel_toggles_stay_in_the_tui() {
+        use crossterm::event::KeyEvent;
+
+        let mut tui = Tui::headless(Config::default());
+        let mut press = |layout, code| {
+            tui.panel_layout = layout;
+            let key = CrosstermEvent::Key(KeyEvent::new(code, KeyModifiers::NONE));
+            let event = tui.handle_crossterm_event(key).expect("nothing is drawn");
+            assert!(event.is_none(), "{code:?} would be sent to the host");
+            tui.panel_layout
+        };
+
+        assert_eq!(
+            press(PanelLayout::Both, KeyCode::Char('1')),
+            PanelLayout::TimerOnly
+        );
+        assert_eq!(
+            press(PanelLayout::Both, KeyCode::Char('2')),
+            PanelLayout::SettingsOnly
+        );
+        assert_eq!(
+            press(PanelLayout::Both, KeyCode::Tab),
+            PanelLayout::SettingsOnly
+        );
+        assert_eq!(
+            press(PanelLayout::Both, KeyCode::BackTab),
+            PanelLayout::TimerOnly
+        );
+        // refused with a hint, which isn't sent either
+        assert_eq!(
+            press(PanelLayout::TimerOnly, KeyCode::Char('2')),
+            PanelLayout::TimerOnly
+        );
+    }
+
+    #[test]
+    fn only_listed_clients_can_be_disconnected() {
+        use crossterm::event::KeyEvent;
+
+        let mut tui = Tui::headless(Config::default());
+        tui.show_client_list = true;
+        tui.last_display_data = Some(DisplayData {
+            network_status: NetworkStatus::Server {
+                connected_clients: Vec::new(),
+                listening_on: Vec::new(),
+            },
+            ..drawn(&State::new(Settings::default()))
+        });
+
+        let key = CrosstermEvent::Key(KeyEvent::new(KeyCode::Char('1'), KeyModifiers::NONE));
+        let action = tui.handle_crossterm_event(key).expect("nothing is drawn");
+        assert_eq!(action, None);
+        assert!(tui.show_client_list);
     }
 }
@@ -1,16 +1,60 @@
+use crate::tui::CounterGlyphs;
 use itertools::intersperse;
 use std::iter;
 use unicode_segmentation::UnicodeSegmentation;
 
-pub fn session_counter(n_highlighted_indicators: usize, n_indicators: usize) -> String {
-    let counter = "▢".repeat(n_highlighted_indicators)
-        + &"-".repeat(n_indicators.saturating_sub(n_highlighted_indicators));
-    intersperse(counter.graphemes(true), " ").collect()
+pub fn session_counter(
+    n_highlighted_indicators: usize,
+    n_indicators: usize,
+    glyphs: &CounterGlyphs,
+) -> String {
+    let n_empty_indicators = n_indicators.saturating_sub(n_highlighted_indicators);
+    let indicators = iter::repeat_n(glyphs.filled.as_str(), n_highlighted_indicators)
+        .chain(iter::repeat_n(glyphs.empty.as_str(), n_empty_indicators));
+    intersperse(indicators, glyphs.separator.as_str()).collect()
 }
 
-pub fn clock(percentage: f64) -> String {
-    let percentage = percentage.max(0.0).min(1.0);
+const BOX_WIDTH: usize = 21;
+const BOX_HEIGHT: usize = 11;
+const N_BOX_ELEMENTS: usize = 60;
+
+/// Number of bars of the clock that are drawn, the last one is drawn as soon as any of it remains.
+fn n_drawn_bars(percentage: f64) -> usize {
+    (N_BOX_ELEMENTS as f64 * percentage.clamp(0.0, 1.0)).ceil() as usize
+}
+
+/// Row and column of every bar of the clock, in the order in which they disappear.
+fn clock_path() -> Vec<(usize, usize)> {
+    let mut path: Vec<(usize, usize)> = Vec::new();
+    path.extend(iter::repeat(0).zip((0..BOX_WIDTH / 2).rev()));
+    path.extend((1..BOX_HEIGHT).zip(iter::repeat(0)));
+    path.extend(iter::repeat(BOX_HEIGHT - 1).zip(1..BOX_WIDTH));
+    path.extend((0..BOX_HEIGHT - 1).rev().zip(iter::repeat(BOX_WIDTH - 1)));
+    path.extend(iter::repeat(0).zip(((BOX_WIDTH - BOX_WIDTH / 2 - 1)..(BOX_WIDTH - 1)).rev()));
+    path
+}
+
+/// Row and column of the bar that disappears next, the edge of the progress.
+/// `None` if the clock is full or empty.
+pub fn clock_edge(percentage: f64) -> Option<(usize, usize)> {
+    let draw_n_bars = n_drawn_bars(percentage);
 
+    match draw_n_bars {
+        0 | N_BOX_ELEMENTS => None,
+        _ => clock_path().get(N_BOX_ELEMENTS - draw_n_bars).copied(),
+    }
+}
+
+/// The lighter variant of a bar of the clock, drawn at its edge.
+pub fn ghost_glyph(glyph: &str) -> &str {
+    match glyph {
+        "─" => "╌",
+        "│" => "╎",
+        _ => glyph,
+    }
+}
+
+pub fn clock(percentage: f64) -> String {
     const WHOLE_BOX: &str = "╭───────────────────╮
 │                   │
 │                   │
@@ -23,25 +67,13 @@ pub fn clock(percentage: f64) -> String {
 │                   │
 ╰───────────────────╯";
 
-    const BOX_WIDTH: usize = 21;
-    const BOX_HEIGHT: usize = 11;
-    const N_BOX_ELEMENTS: usize = 60;
-
-    let draw_n_bars = (N_BOX_ELEMENTS as f64 * percentage).ceil() as usize;
-    let skip_n_bars = N_BOX_ELEMENTS - draw_n_bars;
+    let skip_n_bars = N_BOX_ELEMENTS - n_drawn_bars(percentage);
     let mut grapheme_matrix: Vec<Vec<&str>> = WHOLE_BOX
         .lines()
         .map(|line| line.graphemes(true).collect())
         .collect();
 
-    let mut path: Vec<(usize, usize)> = Vec::new();
-    path.extend(iter::repeat(0).zip((0..BOX_WIDTH / 2).rev()));
-    path.extend((1..BOX_HEIGHT).zip(iter::repeat(0)));
-    path.extend(iter::repeat(BOX_HEIGHT - 1).zip(1..BOX_WIDTH));
-    path.extend((0..BOX_HEIGHT - 1).rev().zip(iter::repeat(BOX_WIDTH - 1)));
-    path.extend(iter::repeat(0).zip(((BOX_WIDTH - BOX_WIDTH / 2 - 1)..(BOX_WIDTH - 1)).rev()));
-
-    for (row, col) in path.iter().take(skip_n_bars) {
+    for (row, col) in clock_path().iter().take(skip_n_bars) {
         grapheme_matrix[*row][*col] = " ";
     }
 
@@ -54,3 +86,101 @@ pub fn clock(percentage: f64) -> String {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drawn_bars(clock: &str) -> usize {
+        clock
+            .graphemes(true)
+            .filter(|g| !g.trim().is_empty())
+            .count()
+    }
+
+    #[test]
+    fn empty_clock_draws_nothing() {
+        assert_eq!(drawn_bars(&clock(0.0)), 0);
+        assert_eq!(drawn_bars(&clock(-1.0)), 0);
+    }
+
+    #[test]
+    fn full_clock_fills_the_whole_box() {
+        let expected = [
+            "╭───────────────────╮",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "│                   │",
+            "╰───────────────────╯",
+        ];
+        assert_eq!(clock(1.0).lines().collect::<Vec<_>>(), expected);
+        assert_eq!(clock(2.0), clock(1.0));
+        assert_eq!(drawn_bars(&clock(1.0)), N_BOX_ELEMENTS);
+    }
+
+    #[test]
+    fn fill_never_decreases_as_progress_rises() {
+        let mut previous = 0;
+        for step in 0..=600 {
+            let drawn = drawn_bars(&clock(f64::from(step) / 600.0));
+            assert!(
+                drawn >= previous,
+                "{drawn} bars at step {step}, {previous} before"
+            );
+            previous = drawn;
+        }
+        assert_eq!(previous, N_BOX_ELEMENTS);
+    }
+
+    #[test]
+    fn every_clock_has_the_same_size() {
+        for percentage in [0.0, 0.1, 0.5, 0.99, 1.0] {
+            let clock = clock(percentage);
+            assert_eq!(clock.lines().count(), BOX_HEIGHT);
+            assert!(clock
+                .lines()
+                .all(|line| line.graphemes(true).count() == BOX_WIDTH));
+        }
+    }
+
+    #[test]
+    fn half_clock_snapshot() {
+        let expected = [
+            "          ──────────╮",
+            "                    │",
+            "                    │",
+            "                    │",
+            "                    │",
+            "                    │",
+            "                    │",
+            "                    │",
+            "                    │",
+            "                    │",
+            "           ─────────╯",
+        ];
+        let clock = clock(0.5);
+        let lines: Vec<_> = clock.lines().map(str::trim_end).collect();
+        let expected: Vec<_> = expected.iter().map(|line| line.trim_end()).collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn last_bar_snapshot() {
+        let clock = clock(1.0 / N_BOX_ELEMENTS as f64);
+        assert_eq!(clock.lines().next(), Some("          ─          "));
+        assert!(clock.lines().skip(1).all(|line| line.trim().is_empty()));
+        assert_eq!(clock_edge(1.0 / N_BOX_ELEMENTS as f64), Some((0, 10)));
+    }
+
+    #[test]
+    fn full_and_empty_clocks_have_no_edge() {
+        assert_eq!(clock_edge(0.0), None);
+        assert_eq!(clock_edge(1.0), None);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::protocol::Event;
+use crate::strings::strings;
+use crossterm::event::KeyCode;
+use std::time::Duration;
+
+/// Keys that trigger app events. The legends of the tui are generated from these.
+#[derive(Clone)]
+pub struct KeyBindings {
+    pub toggle_timer: KeyCode,
+    pub extend_activity: KeyCode,
+    pub reduce_activity: KeyCode,
+    pub skip_activity: KeyCode,
+    pub end_focus_early: KeyCode,
+    pub reset_timer: KeyCode,
+    pub quit: KeyCode,
+    pub client_list: KeyCode,
+    pub request_sync: KeyCode,
+    /// Starts typing a line of text for every client, only while hosting.
+    pub announce: KeyCode,
+    /// Saves the current frame as text, not shown in the legends.
+    pub snapshot: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            toggle_timer: KeyCode::Char(' '),
+            extend_activity: KeyCode::Up,
+            reduce_activity: KeyCode::Down,
+            skip_activity: KeyCode::Char('s'),
+            end_focus_early: KeyCode::Char('e'),
+            reset_timer: KeyCode::Char('r'),
+            quit: KeyCode::Char('q'),
+            client_list: KeyCode::Char('c'),
+            request_sync: KeyCode::F(5),
+            announce: KeyCode::Char('a'),
+            snapshot: KeyCode::F(12),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub const ADJUSTMENT_STEP: Duration = Duration::from_secs(60);
+
+    pub fn event(&self, key: KeyCode) -> Option<Event> {
+        match key {
+            k if k == self.toggle_timer => Some(Event::ToggleTimer),
+            k if k == self.extend_activity => Some(Event::ExtendActivity(Self::ADJUSTMENT_STEP)),
+            k if k == self.reduce_activity => Some(Event::ReduceActivity(Self::ADJUSTMENT_STEP)),
+            k if k == self.skip_activity => Some(Event::SkipActivity),
+            k if k == self.end_focus_early => Some(Event::EndFocusEarly),
+            k if k == self.reset_timer => Some(Event::ResetTimer),
+            k if k == self.quit => Some(Event::Quit),
+            k if k == self.request_sync => Some(Event::RequestSync),
+            _ => None,
+        }
+    }
+
+    /// With Shift, the adjustment keys change every later activity of the current kind too.
+    pub fn shifted_event(&self, key: KeyCode) -> Option<Event> {
+        match key {
+            k if k == self.extend_activity => {
+                Some(Event::ExtendActivityPermanently(Self::ADJUSTMENT_STEP))
+            }
+            k if k == self.reduce_activity => {
+                Some(Event::ReduceActivityPermanently(Self::ADJUSTMENT_STEP))
+            }
+            _ => None,
+        }
+    }
+
+    /// `plain` spells out the keys and doesn't rely on highlighting, see `Config::accessible`.
+    pub fn timer_legend(&self, plain: bool) -> Vec<String> {
+        let adjust_label = match (self.extend_activity, self.reduce_activity, plain) {
+            (KeyCode::Up, KeyCode::Down, false) => "↕".to_string(),
+            (extend, reduce, false) => key_label(extend, plain) + &key_label(reduce, plain),
+            (extend, reduce, true) => {
+                format!("{}/{}", key_label(extend, plain), key_label(reduce, plain))
+            }
+        };
+        let shifted_adjust_label = match plain {
+            true => format!("shift+{adjust_label}"),
+            false => format!("⇧{adjust_label}"),
+        };
+
+        vec![
+            legend_entry(
+                &key_label(self.toggle_timer, plain),
+                strings().toggle,
+                plain,
+            ),
+            legend_entry(&adjust_label, strings().adjust, plain),
+            legend_entry(&shifted_adjust_label, strings().adjust_all, plain),
+            legend_entry(&key_label(self.skip_activity, plain), strings().skip, plain),
+            legend_entry(
+                &key_label(self.end_focus_early, plain),
+                strings().end_focus,
+                plain,
+            ),
+            legend_entry(&key_label(self.reset_timer, plain), strings().reset, plain),
+            legend_entry(&key_label(self.quit, plain), strings().quit, plain),
+        ]
+    }
+
+    pub fn settings_legend_host(&self, plain: bool) -> Vec<String> {
+        vec![
+            legend_entry(
+                &key_label(self.client_list, plain),
+                strings().clients,
+                plain,
+            ),
+            legend_entry(&key_label(self.announce, plain), strings().announce, plain),
+        ]
+    }
+
+    pub fn settings_legend_client(&self, plain: bool) -> Vec<String> {
+        vec![legend_entry(
+            &key_label(self.request_sync, plain),
+            strings().sync,
+            plain,
+        )]
+    }
+}
+
+/// `plain` uses words instead of symbols.
+fn key_label(key: KeyCode, plain: bool) -> String {
+    match (key, plain) {
+        (KeyCode::Char(' '), false) => "␣".to_string(),
+        (KeyCode::Char(' '), true) => "space".to_string(),
+        (KeyCode::Char(c), _) => c.to_string(),
+        (KeyCode::Up, false) => "↑".to_string(),
+        (KeyCode::Down, false) => "↓".to_string(),
+        (KeyCode::Left, false) => "←".to_string(),
+        (KeyCode::Right, false) => "→".to_string(),
+        (KeyCode::F(n), _) => format!("F{n}"),
+        (other, _) => format!("{other:?}").to_lowercase(),
+    }
+}
+
+/// Only shows the description if it starts with the key, because the first grapheme of a legend entry is highlighted.
+/// `plain` legends aren't highlighted, they always name the key.
+fn legend_entry(label: &str, description: &str, plain: bool) -> String {
+    if plain {
+        format!("{label}: {description}")
+    } else if description.starts_with(label) {
+        description.to_string()
+    } else {
+        format!("{label} {description}")
+    }
+}
@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    buffer::Cell,
+    layout::Rect,
+};
+
+/// Draws everything `top` lines further down, so that the tui can be drawn below the cursor without the alternate screen.
+/// tui's fixed viewports can't be used for that, because their position is ignored when drawing.
+pub struct OffsetBackend {
+    inner: CrosstermBackend<io::Stdout>,
+    top: u16,
+}
+
+impl OffsetBackend {
+    pub fn new(top: u16) -> Self {
+        OffsetBackend {
+            inner: CrosstermBackend::new(io::stdout()),
+            top,
+        }
+    }
+}
+
+impl Write for OffsetBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Write::flush(&mut self.inner)
+    }
+}
+
+impl Backend for OffsetBackend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        let top = self.top;
+        self.inner
+            .draw(content.map(|(x, y, cell)| (x, y.saturating_add(top), cell)))
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.inner.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.inner.show_cursor()
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        let (x, y) = self.inner.get_cursor()?;
+        Ok((x, y.saturating_sub(self.top)))
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.inner.set_cursor(x, y.saturating_add(self.top))
+    }
+
+    /// Only clears the lines from `top` on.
+    fn clear(&mut self) -> io::Result<()> {
+        if self.top == 0 {
+            return self.inner.clear();
+        }
+        crossterm::execute!(
+            self.inner,
+            crossterm::cursor::MoveTo(0, self.top),
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown),
+        )
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        let size = self.inner.size()?;
+        Ok(Rect {
+            height: size.height.saturating_sub(self.top),
+            ..size
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Backend::flush(&mut self.inner)
+    }
+}
@@ -0,0 +1,37 @@
+//! Doesn't depend on the rest of the crate, so that `tests/panic_hook.rs` can test it in a process of its own.
+
+use std::panic::{self, PanicHookInfo};
+use std::sync::Arc;
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+/// A panic hook that restores the terminal before the previously installed panic hook prints the panic message.
+pub struct TerminalPanicHook {
+    /// Shared with the installed hook, which calls it.
+    previous: Arc<PanicHook>,
+}
+
+impl TerminalPanicHook {
+    pub fn install(restore: impl Fn() + Sync + Send + 'static) -> Self {
+        let previous: Arc<PanicHook> = Arc::new(panic::take_hook());
+        let hook_previous = previous.clone();
+        panic::set_hook(Box::new(move |panic_info| {
+            restore();
+            hook_previous(panic_info);
+        }));
+
+        Self { previous }
+    }
+
+    /// Puts the previous hook itself back, rather than a hook that calls it,
+    /// so that hooks don't pile up when the tui is enabled again.
+    pub fn uninstall(self) {
+        // drops the installed hook, which holds the other reference to the previous one
+        drop(panic::take_hook());
+        match Arc::try_unwrap(self.previous) {
+            Ok(previous) => panic::set_hook(previous),
+            // another thread may still be running the installed hook while it panics
+            Err(previous) => panic::set_hook(Box::new(move |panic_info| previous(panic_info))),
+        }
+    }
+}
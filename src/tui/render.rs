@@ -1,12 +1,16 @@
 use crate::app::NetworkStatus;
+use crate::pomodoro::SessionDuration;
 use crate::protocol::TimerVisuals;
+use crate::strings::strings;
+use crate::tui::offset_backend::OffsetBackend;
 use crate::tui::widgets::{BlockWithLegend, PomodoroClock, Settings};
-use std::io;
+use crate::tui::{PanelLayout, Theme};
+use std::net::SocketAddr;
+use std::time::Duration;
 use tui::widgets::BorderType;
 use tui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
     text::{Span, Spans},
     widgets, Frame,
 };
@@ -21,13 +25,30 @@ fn split_initial(str: &str) -> (&str, &str) {
     (initial, remainder)
 }
 
-fn define_block<'a>(title: &'a str, legend: Vec<&'a str>) -> BlockWithLegend<'a> {
+/// How the user interface is arranged, independent of the displayed data.
+pub struct View<'a> {
+    pub panel_layout: PanelLayout,
+    pub settings_percentage: u16,
+    pub show_client_list: bool,
+    pub hint: Option<&'a str>,
+    /// Wall time since the session started, including breaks and pauses.
+    pub session_elapsed: Option<Duration>,
+    /// Projected end of the last focus session as minutes since midnight.
+    pub done_by: Option<u16>,
+    pub focus_sessions_today: Option<u64>,
+    pub focus_break_ratio: Option<&'a str>,
+    /// The dimmed step of the idle animation, see `Tui::set_idle_dimmed`.
+    pub idle_dimmed: bool,
+    pub theme: &'a Theme,
+    pub timer_legend: &'a [String],
+    pub settings_legend: &'a [String],
+}
+
+fn define_block<'a>(title: &'a str, legend: &'a [String], theme: &Theme) -> BlockWithLegend<'a> {
     let (initial, remainder) = split_initial(title);
 
-    let text_style = Style::default()
-        .fg(Color::White)
-        .add_modifier(Modifier::BOLD);
-    let initials_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let text_style = theme.title;
+    let initials_style = theme.title_initial;
 
     let title = Spans::from(vec![
         Span::styled(initial, initials_style),
@@ -35,7 +56,7 @@ fn define_block<'a>(title: &'a str, legend: Vec<&'a str>) -> BlockWithLegend<'a>
     ]);
 
     let legend = legend
-        .into_iter()
+        .iter()
         .map(|s| {
             let (initial, remainder) = split_initial(s);
 
@@ -53,19 +74,191 @@ fn define_block<'a>(title: &'a str, legend: Vec<&'a str>) -> BlockWithLegend<'a>
         .legend(legend)
 }
 
-pub fn render_ui(
-    frame: &mut Frame<CrosstermBackend<io::Stdout>>,
+/// The contents of the settings panel.
+fn status_text(
     timer_visuals: &TimerVisuals,
     network_status: &NetworkStatus,
-    show_settings: bool,
-    show_timer: bool,
+    view: &View,
+) -> String {
+    let network_info_text = match network_status {
+        NetworkStatus::Offline => strings().offline.to_string(),
+        NetworkStatus::Server {
+            connected_clients,
+            listening_on,
+        } => {
+            let port = listening_on
+                .first()
+                .map(SocketAddr::port)
+                .unwrap_or_default();
+            let mut text = (strings().listening_on_port)(port);
+            if listening_on.len() > 1 {
+                text += "\nIPv4 + IPv6";
+            }
+            format!(
+                "{text}\n{}",
+                (strings().clients_connected)(connected_clients.len())
+            )
+        }
+        NetworkStatus::Reconnecting { attempt, next_in } => {
+            // rounded up, so that it doesn't show 0s before the attempt is made
+            (strings().reconnecting)(*attempt, next_in.as_secs_f64().ceil() as u64)
+        }
+        NetworkStatus::Client {
+            connected_to,
+            room,
+            stale_for,
+        } => {
+            let mut text = (strings().connected_to)(&connected_to.to_string());
+            if let Some(name) = room {
+                text += "\n";
+                text += &(strings().room)(name);
+            }
+            text += "\n";
+            text += &match stale_for {
+                None => strings().link_live.to_string(),
+                Some(duration) => (strings().link_stale)(duration.as_secs()),
+            };
+            text
+        }
+    };
+
+    let mut network_info_text = format!(
+        "{network_info_text}\n{}",
+        (strings().streak)(timer_visuals.focus_streak)
+    );
+    if let Some(n) = timer_visuals.focus_sessions_until_long_break {
+        network_info_text += "\n";
+        network_info_text += &(strings().until_long_break)(n);
+    }
+    if let Some(focus_break_ratio) = view.focus_break_ratio {
+        network_info_text += "\n";
+        network_info_text += focus_break_ratio;
+    }
+    if let Some(elapsed) = view.session_elapsed {
+        network_info_text += "\n";
+        network_info_text += &(strings().elapsed)(&SessionDuration(elapsed).to_string());
+    }
+    if let Some(minute_of_day) = view.done_by {
+        network_info_text += "\n";
+        network_info_text += &(strings().done_by)(&format!(
+            "{:02}:{:02}",
+            minute_of_day / 60,
+            minute_of_day % 60
+        ));
+    }
+
+    network_info_text
+}
+
+/// Plain text without box drawing characters or symbols, one piece of information per line,
+/// so that screen readers can read it, see `Config::accessible`.
+pub fn render_accessible(
+    frame: &mut Frame<OffsetBackend>,
+    timer_visuals: &TimerVisuals,
+    network_status: &NetworkStatus,
+    view: &View,
 ) {
+    let style = view
+        .theme
+        .clock_style(timer_visuals.activity, timer_visuals.timer_is_paused);
+    let activity_line = match timer_visuals.preparation_remaining {
+        Some(remaining) => (strings().starting_in)(remaining.as_secs_f64().ceil() as u64),
+        None => format!(
+            "{}, {}",
+            timer_visuals.activity,
+            match timer_visuals.timer_is_paused {
+                true => strings().paused,
+                false => strings().running,
+            }
+        ),
+    };
+
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            (strings().remaining)(&timer_visuals.time_remaining.to_string()),
+            style.add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(Span::styled(activity_line, style)),
+    ];
+    if timer_visuals.session_counter_total > 1 {
+        lines.push(Spans::from((strings().session_of)(
+            timer_visuals.session_counter_highlighted,
+            timer_visuals.session_counter_total,
+        )));
+    }
+    if let Some(focus_sessions_today) = view.focus_sessions_today {
+        lines.push(Spans::from((strings().today)(focus_sessions_today)));
+    }
+    if let Some(hint) = view.hint {
+        lines.push(Spans::from(Span::styled(hint.to_string(), view.theme.hint)));
+    }
+    lines.push(Spans::default());
+    lines.extend(
+        status_text(timer_visuals, network_status, view)
+            .lines()
+            .map(|line| Spans::from(line.to_string())),
+    );
+    if let (
+        true,
+        NetworkStatus::Server {
+            connected_clients, ..
+        },
+    ) = (view.show_client_list, network_status)
+    {
+        lines.push(Spans::default());
+        lines.push(Spans::from(strings().client_list_title.trim().to_string()));
+        lines.extend(
+            connected_clients
+                .iter()
+                .enumerate()
+                .map(|(i, client)| Spans::from(format!("{} {client}", i + 1))),
+        );
+    }
+    let legend = [view.timer_legend, view.settings_legend].concat();
+    if !legend.is_empty() {
+        lines.push(Spans::default());
+        lines.push(Spans::from(Span::styled(
+            (strings().keys)(&legend.join(", ")),
+            view.theme.title,
+        )));
+    }
+
+    let paragraph = widgets::Paragraph::new(lines).wrap(widgets::Wrap { trim: false });
+    frame.render_widget(paragraph, frame.size());
+}
+
+/// The clock without its block. Hosts and clients draw it from the same `TimerVisuals`.
+fn clock_widget<'a>(timer_visuals: &TimerVisuals, theme: &Theme) -> PomodoroClock<'a> {
+    PomodoroClock::new(
+        timer_visuals.session_counter_highlighted,
+        timer_visuals.session_counter_total,
+        timer_visuals.progress_percentage,
+        timer_visuals.time_remaining,
+        timer_visuals.activity,
+        timer_visuals.timer_is_paused,
+    )
+    .preparation_remaining(timer_visuals.preparation_remaining)
+    .style(theme.clock_style(timer_visuals.activity, timer_visuals.timer_is_paused))
+    .counter_glyphs(theme.counter.clone())
+    .edge_modifier(theme.clock_edge)
+}
+
+/// Returns the area of the clock if it is visible, so that mouse events can be mapped to it.
+pub fn render_ui(
+    frame: &mut Frame<OffsetBackend>,
+    timer_visuals: &TimerVisuals,
+    network_status: &NetworkStatus,
+    view: &View,
+) -> Option<Rect> {
+    let mut clock_area = None;
+    let panel_layout = view.panel_layout;
+    let settings_percentage = view.settings_percentage;
+
     let (settings_chunk, timer_chunk) = {
-        let (settings_pct, timer_pct) = match (show_settings, show_timer) {
-            (true, true) => (20, 80),
-            (true, false) => (100, 0),
-            (false, true) => (0, 100),
-            (false, false) => (0, 0),
+        let (settings_pct, timer_pct) = match panel_layout {
+            PanelLayout::Both => (settings_percentage, 100 - settings_percentage),
+            PanelLayout::SettingsOnly => (100, 0),
+            PanelLayout::TimerOnly => (0, 100),
         };
 
         let direction = if frame.size().width >= frame.size().height * 2 {
@@ -85,60 +278,251 @@ pub fn render_ui(
         (toplevel_chunks[0], toplevel_chunks[1])
     };
 
-    if show_settings {
-        let network_info_text = {
-            match &network_status {
-                NetworkStatus::Offline => "offline".to_string(),
-                NetworkStatus::Server {
-                    connected_clients,
-                    listening_on,
-                } => {
-                    let how_many_clients = match connected_clients.len() {
-                        0 => "no clients".to_string(),
-                        1 => "one client".to_string(),
-                        n => format!("{n} clients"),
-                    };
-
-                    format!(
-                        "listening on port {}\n{how_many_clients} connected",
-                        listening_on.port()
-                    )
-                }
-                NetworkStatus::Client { connected_to } => {
-                    format!("connected to {connected_to}")
-                }
-            }
-        };
+    if panel_layout.shows_settings() {
+        let network_info_text = status_text(timer_visuals, network_status, view);
 
         let settings_widget = Settings::default()
             .network_status(&network_info_text)
-            .block(define_block("¹settings", vec![]));
+            .block(define_block(
+                strings().settings_title,
+                view.settings_legend,
+                view.theme,
+            ));
 
         frame.render_widget(settings_widget, settings_chunk);
     }
-    if show_timer {
-        let is_fourth_session = timer_visuals.completed_focus_sessions % 4 == 0;
-        let n_highlighted_indicators = timer_visuals.completed_focus_sessions as u8 % 4
-            + match (timer_visuals.activity.is_focus(), is_fourth_session) {
-                (true, _) => 1,
-                (false, true) => 4,
-                _ => 0,
-            };
-
-        let timer_widget = PomodoroClock::new(
-            timer_visuals.completed_focus_sessions,
-            n_highlighted_indicators,
-            4,
-            timer_visuals.progress_percentage,
-            timer_visuals.time_remaining,
-            timer_visuals.activity,
-            timer_visuals.timer_is_paused,
-        )
-        .block(define_block(
-            "²timer",
-            vec!["␣ toggle", "↕ adjust", "skip", "reset", "quit"],
-        ));
+    if panel_layout.shows_timer() {
+        let mut timer_block = define_block(strings().timer_title, view.timer_legend, view.theme);
+        if view.idle_dimmed {
+            timer_block =
+                timer_block.border_style(Style::default().add_modifier(view.theme.paused));
+        }
+        clock_area = PomodoroClock::clock_area(timer_block.inner(timer_chunk));
+        let timer_widget = clock_widget(timer_visuals, view.theme).block(timer_block);
 
         frame.render_widget(timer_widget, timer_chunk);
+
+        if let Some(focus_sessions_today) = view.focus_sessions_today {
+            let badge = format!(" {} ", (strings().today)(focus_sessions_today));
+            let badge_width = badge.graphemes(true).count() as u16;
+            // keep the title visible
+            if badge_width + 12 <= timer_chunk.width {
+                let badge_area = Rect {
+                    x: timer_chunk.right() - badge_width - 1,
+                    y: timer_chunk.y,
+                    width: badge_width,
+                    height: 1,
+                };
+                frame.render_widget(widgets::Paragraph::new(badge), badge_area);
+            }
+        }
+    }
+
+    if let (
+        true,
+        NetworkStatus::Server {
+            connected_clients, ..
+        },
+    ) = (view.show_client_list, network_status)
+    {
+        let client_list_text = if connected_clients.is_empty() {
+            (strings().clients_connected)(0)
+        } else {
+            connected_clients
+                .iter()
+                .enumerate()
+                .map(|(i, client)| format!("{} {client}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let client_list_area = {
+            let width = (client_list_text
+                .lines()
+                .map(|l| l.graphemes(true).count())
+                .max()
+                .unwrap_or_default() as u16
+                + 4)
+            .max(34)
+            .min(frame.size().width);
+            let height = (client_list_text.lines().count() as u16 + 2).min(frame.size().height);
+
+            Rect {
+                x: frame.size().x + (frame.size().width - width) / 2,
+                y: frame.size().y + (frame.size().height - height) / 2,
+                width,
+                height,
+            }
+        };
+
+        let client_list_widget = widgets::Paragraph::new(client_list_text)
+            .alignment(Alignment::Center)
+            .block(
+                widgets::Block::default()
+                    .borders(widgets::Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .title(strings().client_list_title),
+            );
+
+        frame.render_widget(widgets::Clear, client_list_area);
+        frame.render_widget(client_list_widget, client_list_area);
+    }
+
+    if let Some(hint) = view.hint {
+        let hint_area = Rect {
+            height: 1,
+            ..frame.size().inner(&Margin {
+                horizontal: 2,
+                vertical: 0,
+            })
+        };
+        let hint_widget =
+            widgets::Paragraph::new(Span::styled(format!(" {hint} "), view.theme.hint))
+                .alignment(Alignment::Right);
+
+        frame.render_widget(hint_widget, hint_area);
+    }
+
+    clock_area
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::{self, Activity, Settings, State};
+    use crate::protocol::NetworkProtocol;
+    use crate::tui::animation;
+    use tui::buffer::Buffer;
+    use tui::widgets::Widget;
+    use zwiesel::Message;
+
+    /// The visuals as a client receives them from the host.
+    fn sent_to_client(visuals: TimerVisuals) -> TimerVisuals {
+        let bytes = NetworkProtocol::Display(visuals)
+            .serialize()
+            .expect("visuals can be serialized");
+        match NetworkProtocol::deserialize(bytes) {
+            Ok(NetworkProtocol::Display(visuals)) => visuals,
+            _ => panic!("the frame didn't survive the round trip"),
+        }
+    }
+
+    fn render_clock(timer_visuals: &TimerVisuals, theme: &Theme) -> Buffer {
+        let area = Rect::new(0, 0, 30, 13);
+        let mut buffer = Buffer::empty(area);
+        clock_widget(timer_visuals, theme).render(area, &mut buffer);
+        buffer
+    }
+
+    fn buffer_lines(buffer: &Buffer) -> Vec<String> {
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                (area.left()..area.right())
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn host_and_clients_render_the_same_clock() {
+        let theme = Theme::default();
+        for sessions_before_long_break in [None, Some(1), Some(2), Some(4)] {
+            for long_break_after in [None, Some(1)] {
+                let settings = Settings {
+                    sessions_before_long_break,
+                    long_break_after,
+                    ..Settings::default()
+                };
+                let mut state = State::new(settings);
+
+                for step in 0..24 {
+                    state.set_progress_fraction(f64::from(step) / 24.0);
+                    let host = TimerVisuals::from(&state);
+                    let client = sent_to_client(host);
+                    assert!(host == client, "visuals changed on the way to the client");
+
+                    let (highlighted, total) = pomodoro::session_counter(
+                        state.completed_focus_sessions(),
+                        state.current_activity(),
+                        sessions_before_long_break,
+                        state.settings().long_break_position(),
+                    );
+                    assert_eq!(
+                        (host.session_counter_highlighted, host.session_counter_total),
+                        (highlighted, total)
+                    );
+                    assert_eq!(total, sessions_before_long_break.unwrap_or(1));
+                    assert!(highlighted <= total);
+                    if state.current_activity() == Activity::LongBreak {
+                        assert_eq!(highlighted, total);
+                    }
+
+                    let host_clock = render_clock(&host, &theme);
+                    assert_eq!(host_clock, render_clock(&client, &theme));
+                    let counter = animation::session_counter(
+                        highlighted as usize,
+                        total as usize,
+                        &theme.counter,
+                    );
+                    assert!(
+                        buffer_lines(&host_clock)
+                            .iter()
+                            .any(|line| line.contains(&format!(" {counter} "))),
+                        "{counter:?} is not drawn"
+                    );
+
+                    state.skip_activity();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clients_show_the_cadence_of_the_host() {
+        let theme = Theme::default();
+        let mut state = State::new(Settings {
+            sessions_before_long_break: Some(6),
+            ..Settings::default()
+        });
+
+        let mut shown = Vec::new();
+        for _ in 0..2 * 6 {
+            let client = sent_to_client(TimerVisuals::from(&state));
+            let (highlighted, total) = (
+                client.session_counter_highlighted,
+                client.session_counter_total,
+            );
+            let counter =
+                animation::session_counter(highlighted as usize, total as usize, &theme.counter);
+            assert!(
+                buffer_lines(&render_clock(&client, &theme))
+                    .iter()
+                    .any(|line| line.contains(&format!(" {counter} "))),
+                "{counter:?} is not drawn"
+            );
+            shown.push((state.current_activity(), highlighted, total));
+            state.skip_activity();
+        }
+
+        use Activity::*;
+        assert_eq!(
+            shown,
+            [
+                (Focus, 1, 6),
+                (ShortBreak, 1, 6),
+                (Focus, 2, 6),
+                (ShortBreak, 2, 6),
+                (Focus, 3, 6),
+                (ShortBreak, 3, 6),
+                (Focus, 4, 6),
+                (ShortBreak, 4, 6),
+                (Focus, 5, 6),
+                (ShortBreak, 5, 6),
+                (Focus, 6, 6),
+                (LongBreak, 6, 6),
+            ]
+        );
     }
 }
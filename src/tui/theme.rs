@@ -0,0 +1,182 @@
+use crate::pomodoro::Activity;
+use clap::ValueEnum;
+use std::env;
+use std::fmt::{Display, Formatter};
+use tui::style::{Color, Modifier, Style};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Colors of the tui. Only the basic terminal colors are used by default,
+/// so that the terminal's palette adapts them to light and dark backgrounds.
+#[derive(Clone)]
+pub struct Theme {
+    pub focus: Color,
+    pub short_break: Color,
+    pub long_break: Color,
+    /// Applied on top of the activity's color while the timer is paused.
+    pub paused: Modifier,
+    /// Titles and legends of the panels.
+    pub title: Style,
+    /// The highlighted first grapheme of titles and legend entries, which is the key to press.
+    pub title_initial: Style,
+    pub hint: Style,
+    pub counter: CounterGlyphs,
+    /// Applied to the bar of the clock that disappears next, so that the progress reads as motion.
+    /// `None` draws the whole clock in the same style.
+    pub clock_edge: Option<Modifier>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            focus: Color::Green,
+            short_break: Color::Blue,
+            long_break: Color::Blue,
+            // dimming keeps the hue and works on any background, unlike a fixed gray
+            paused: Modifier::DIM,
+            title: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            title_initial: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            hint: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            counter: CounterGlyphs::default(),
+            clock_edge: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Only uses the terminal's default colors and text styles.
+    pub fn mono() -> Self {
+        Theme {
+            focus: Color::Reset,
+            short_break: Color::Reset,
+            long_break: Color::Reset,
+            paused: Modifier::DIM,
+            title: Style::default().add_modifier(Modifier::BOLD),
+            title_initial: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            hint: Style::default().add_modifier(Modifier::REVERSED),
+            counter: CounterGlyphs::default(),
+            clock_edge: None,
+        }
+    }
+
+    pub fn for_color_mode(color_mode: ColorMode) -> Self {
+        match color_mode.resolved() {
+            ColorMode::Mono => Self::mono(),
+            _ => Self::default(),
+        }
+    }
+
+    pub fn clock_style(&self, activity: Activity, is_paused: bool) -> Style {
+        let color = match activity {
+            Activity::Focus => self.focus,
+            Activity::ShortBreak => self.short_break,
+            Activity::LongBreak => self.long_break,
+        };
+        let style = Style::default().fg(color);
+
+        if is_paused {
+            style.add_modifier(self.paused)
+        } else {
+            style
+        }
+    }
+}
+
+/// Glyphs of the session counter above the remaining time.
+#[derive(Clone)]
+pub struct CounterGlyphs {
+    /// A completed focus session of the current cycle.
+    pub filled: String,
+    /// A focus session of the current cycle that is still to come.
+    pub empty: String,
+    /// Between two indicators, may be empty.
+    pub separator: String,
+}
+
+impl Default for CounterGlyphs {
+    fn default() -> Self {
+        CounterGlyphs {
+            filled: "▢".to_string(),
+            empty: "-".to_string(),
+            separator: " ".to_string(),
+        }
+    }
+}
+
+impl CounterGlyphs {
+    /// Replaces the given glyphs. The layout expects every glyph to be a single grapheme cluster,
+    /// glyphs that aren't keep their default and are returned as warnings.
+    pub fn replaced(
+        mut self,
+        filled: Option<String>,
+        empty: Option<String>,
+        separator: Option<String>,
+    ) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut replace = |glyph: &mut String, new: Option<String>, may_be_empty: bool| {
+            let Some(new) = new else {
+                return;
+            };
+            match new.graphemes(true).count() {
+                1 => *glyph = new,
+                0 if may_be_empty => *glyph = new,
+                _ => warnings.push(format!(
+                    "{new:?} is not a single character, using {glyph:?} instead"
+                )),
+            }
+        };
+
+        replace(&mut self.filled, filled, false);
+        replace(&mut self.empty, empty, false);
+        replace(&mut self.separator, separator, true);
+
+        (self, warnings)
+    }
+}
+
+/// Whether the tui uses colors. The default theme only uses the 8 basic colors, which every color terminal supports.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ColorMode {
+    /// Colors unless NO_COLOR is set or TERM doesn't support them
+    Auto,
+    /// Always use colors
+    Color,
+    /// Only use text styles like bold and underlined
+    Mono,
+}
+
+impl ColorMode {
+    /// Resolves `Auto` from the environment, following https://no-color.org and the usual TERM values.
+    pub fn resolved(self) -> ColorMode {
+        let ColorMode::Auto = self else {
+            return self;
+        };
+        let non_empty_var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+
+        if non_empty_var("NO_COLOR").is_some() {
+            return ColorMode::Mono;
+        }
+        if non_empty_var("COLORTERM").is_some() {
+            return ColorMode::Color;
+        }
+        match non_empty_var("TERM").as_deref() {
+            Some("dumb") => ColorMode::Mono,
+            Some(term) if term.ends_with("-mono") || term.ends_with("-m") => ColorMode::Mono,
+            // Windows consoles don't set TERM and support colors
+            _ => ColorMode::Color,
+        }
+    }
+}
+
+impl Display for ColorMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorMode::Auto => write!(f, "auto"),
+            ColorMode::Color => write!(f, "color"),
+            ColorMode::Mono => write!(f, "mono"),
+        }
+    }
+}
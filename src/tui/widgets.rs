@@ -1,9 +1,11 @@
 use crate::pomodoro::{Activity, SessionDuration};
-use crate::tui::animation;
+use crate::strings::strings;
+use crate::tui::{animation, CounterGlyphs};
 use std::iter::once;
+use std::time::Duration;
 use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
-    style::Style,
+    style::{Modifier, Style},
     text::{Span, Spans},
     widgets::{Block, BorderType, Borders, Paragraph, Widget, Wrap},
 };
@@ -54,7 +56,6 @@ impl<'a> BlockWithLegend<'a> {
         self
     }
 
-    #[allow(dead_code)]
     pub fn inner(&self, inner: Rect) -> Rect {
         self.block.inner(inner)
     }
@@ -88,7 +89,7 @@ impl<'a> Widget for BlockWithLegend<'a> {
 
         for legend in self.legend.into_iter() {
             let legend: Spans = once(Span::from(symbols.bottom_right))
-                .chain(legend.0.into_iter())
+                .chain(legend.0)
                 .chain(once(Span::from(symbols.bottom_left)))
                 .collect::<Vec<_>>()
                 .into();
@@ -102,13 +103,16 @@ impl<'a> Widget for BlockWithLegend<'a> {
 
 pub struct PomodoroClock<'a> {
     block: Option<BlockWithLegend<'a>>,
-    completed_focus_sessions: u32,
-    break_counter_filled: u8,
-    break_counter_total: u8,
+    break_counter_filled: u32,
+    break_counter_total: u32,
     progress_percentage: f64,
     duration: SessionDuration,
     activity: Activity,
     is_paused: bool,
+    preparation_remaining: Option<Duration>,
+    style: Style,
+    counter_glyphs: CounterGlyphs,
+    edge_modifier: Option<Modifier>,
 }
 
 impl<'a> PomodoroClock<'a> {
@@ -117,17 +121,38 @@ impl<'a> PomodoroClock<'a> {
         self
     }
 
+    /// Shown instead of the activity while counting down to a focus session.
+    pub fn preparation_remaining(mut self, remaining: Option<Duration>) -> PomodoroClock<'a> {
+        self.preparation_remaining = remaining;
+        self
+    }
+
+    /// Style of the clock and the text inside it, not of the block.
+    pub fn style(mut self, style: Style) -> PomodoroClock<'a> {
+        self.style = style;
+        self
+    }
+
+    pub fn counter_glyphs(mut self, glyphs: CounterGlyphs) -> PomodoroClock<'a> {
+        self.counter_glyphs = glyphs;
+        self
+    }
+
+    /// Draws the bar that disappears next with a lighter glyph and this modifier, see `Theme::clock_edge`.
+    pub fn edge_modifier(mut self, modifier: Option<Modifier>) -> PomodoroClock<'a> {
+        self.edge_modifier = modifier;
+        self
+    }
+
     pub fn new(
-        completed_focus_sessions: u32,
-        break_counter_filled: u8,
-        break_counter_total: u8,
+        break_counter_filled: u32,
+        break_counter_total: u32,
         progress_percentage: f64,
         duration: SessionDuration,
         activity: Activity,
         is_paused: bool,
     ) -> Self {
         PomodoroClock {
-            completed_focus_sessions,
             break_counter_filled,
             break_counter_total,
             progress_percentage,
@@ -140,83 +165,50 @@ impl<'a> PomodoroClock<'a> {
 }
 
 impl<'a> Widget for PomodoroClock<'a> {
-    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+    fn render(mut self, area: Rect, buf: &mut tui::buffer::Buffer) {
         let inner_area = match &self.block {
             Some(block) => block.inner(area),
             None => area,
         };
-        if let Some(block) = self.block {
+        if let Some(block) = self.block.take() {
             block.render(area, buf);
         }
 
-        let clock = animation::clock(1.0 - self.progress_percentage);
-        let clock_height = clock.lines().count() as u16;
-        let clock_width = clock
-            .lines()
-            .map(|l| l.graphemes(true).count())
-            .max()
-            .unwrap_or_default() as u16;
-
-        let centered_chunk = {
-            let (left_padding, right_padding);
-            {
-                let leftover_width = inner_area.width.saturating_sub(clock_width);
-                left_padding = leftover_width / 2;
-                right_padding = leftover_width.saturating_sub(left_padding);
-            }
-            let (top_padding, bottom_padding);
-            {
-                let leftover_height = inner_area.height.saturating_sub(clock_height);
-                top_padding = leftover_height / 2;
-                bottom_padding = leftover_height.saturating_sub(top_padding);
-            }
-            let vertically_centered_sub_chunk;
-            {
-                let vertical_sub_chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(top_padding),
-                        Constraint::Length(clock_height),
-                        Constraint::Length(bottom_padding),
-                    ])
-                    .split(inner_area);
-                vertically_centered_sub_chunk = vertical_sub_chunks[1];
-            }
-            let horizontal_sub_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Length(left_padding),
-                    Constraint::Length(clock_width),
-                    Constraint::Length(right_padding),
-                ])
-                .split(vertically_centered_sub_chunk);
-
-            horizontal_sub_chunks[1]
-        };
+        let remaining_percentage = 1.0 - self.progress_percentage;
+        let clock = animation::clock(remaining_percentage);
+        let (clock_width, clock_height) = text_size(&clock);
+        let centered_chunk = centered(inner_area, clock_width, clock_height);
 
         if centered_chunk.height < clock_height || centered_chunk.width < clock_width {
-            InsufficientSpaceWarning::new(
-                (centered_chunk.width, centered_chunk.height),
-                (clock_width, clock_height),
-            )
-            .render(inner_area, buf);
+            self.render_compact(inner_area, buf);
             return;
         }
 
         Paragraph::new(clock)
+            .style(self.style)
             .alignment(Alignment::Left)
             .render(centered_chunk, buf);
 
+        if let (Some(modifier), Some((row, col))) = (
+            self.edge_modifier,
+            animation::clock_edge(remaining_percentage),
+        ) {
+            let cell = buf.get_mut(centered_chunk.x + col as u16, centered_chunk.y + row as u16);
+            let glyph = animation::ghost_glyph(&cell.symbol).to_string();
+            cell.set_symbol(&glyph)
+                .set_style(self.style.add_modifier(modifier));
+        }
+
         let status_text = {
             format!(
-                "{}\n{}\n{} {}",
+                "{}\n{}\n{}",
                 animation::session_counter(
-                    self.break_counter_filled.into(),
-                    self.break_counter_total.into()
+                    self.break_counter_filled as usize,
+                    self.break_counter_total as usize,
+                    &self.counter_glyphs
                 ),
                 self.duration,
-                self.activity,
-                if self.is_paused { "⏵" } else { "⏸" }
+                self.activity_line()
             )
         };
 
@@ -234,22 +226,117 @@ impl<'a> Widget for PomodoroClock<'a> {
             .split(text_chunk)[1];
 
         Paragraph::new(status_text)
+            .style(self.style)
             .alignment(Alignment::Center)
             .render(text_chunk, buf);
     }
 }
 
+impl<'a> PomodoroClock<'a> {
+    fn activity_line(&self) -> String {
+        match self.preparation_remaining {
+            Some(remaining) => (strings().starting_in)(remaining.as_secs_f64().ceil() as u64),
+            None => format!(
+                "{} {}",
+                self.activity,
+                if self.is_paused { "⏵" } else { "⏸" }
+            ),
+        }
+    }
+
+    /// A single line without the clock and the session counter, for areas that are too small for the clock.
+    fn render_compact(&self, inner_area: Rect, buf: &mut tui::buffer::Buffer) {
+        let line = format!("{} {}", self.duration, self.activity_line());
+        let (line_width, line_height) = text_size(&line);
+        let centered_chunk = centered(inner_area, line_width, line_height);
+
+        if centered_chunk.height < line_height || centered_chunk.width < line_width {
+            InsufficientSpaceWarning::new(
+                (inner_area.width, inner_area.height),
+                (line_width, line_height),
+            )
+            .render(inner_area, buf);
+            return;
+        }
+
+        Paragraph::new(line)
+            .style(self.style)
+            .alignment(Alignment::Center)
+            .render(centered_chunk, buf);
+    }
+
+    /// The area the clock is drawn in when `inner_area` is the area inside the block, `None` if it doesn't fit.
+    pub fn clock_area(inner_area: Rect) -> Option<Rect> {
+        let (clock_width, clock_height) = text_size(&animation::clock(1.0));
+        let area = centered(inner_area, clock_width, clock_height);
+
+        (area.width >= clock_width && area.height >= clock_height).then_some(area)
+    }
+}
+
+/// Width and height of a multiline text in graphemes.
+fn text_size(text: &str) -> (u16, u16) {
+    let width = text
+        .lines()
+        .map(|l| l.graphemes(true).count())
+        .max()
+        .unwrap_or_default() as u16;
+
+    (width, text.lines().count() as u16)
+}
+
+/// An area of the given size centered in `area`, cut off if `area` is smaller.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let (left_padding, right_padding);
+    {
+        let leftover_width = area.width.saturating_sub(width);
+        left_padding = leftover_width / 2;
+        right_padding = leftover_width.saturating_sub(left_padding);
+    }
+    let (top_padding, bottom_padding);
+    {
+        let leftover_height = area.height.saturating_sub(height);
+        top_padding = leftover_height / 2;
+        bottom_padding = leftover_height.saturating_sub(top_padding);
+    }
+    let vertically_centered_sub_chunk;
+    {
+        let vertical_sub_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(top_padding),
+                Constraint::Length(height),
+                Constraint::Length(bottom_padding),
+            ])
+            .split(area);
+        vertically_centered_sub_chunk = vertical_sub_chunks[1];
+    }
+    let horizontal_sub_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(left_padding),
+            Constraint::Length(width),
+            Constraint::Length(right_padding),
+        ])
+        .split(vertically_centered_sub_chunk);
+
+    horizontal_sub_chunks[1]
+}
+
 impl<'a> Default for PomodoroClock<'a> {
     fn default() -> PomodoroClock<'a> {
         PomodoroClock {
             block: None,
-            completed_focus_sessions: 0,
             activity: Activity::Focus,
             break_counter_filled: 0,
             break_counter_total: 4,
             progress_percentage: 0.0,
             duration: SessionDuration::default(),
             is_paused: true,
+            preparation_remaining: None,
+            style: Style::default(),
+            counter_glyphs: CounterGlyphs::default(),
+            edge_modifier: None,
         }
     }
 }
@@ -282,8 +369,15 @@ impl<'a> Widget for Settings<'a> {
             block.render(area, buf);
         }
 
+        let network_status = self
+            .network_status
+            .lines()
+            .map(|line| truncate_with_ellipsis(line, inner_area.width.into()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
         let text_area = {
-            let text_height = self.network_status.lines().count() as u16;
+            let text_height = network_status.lines().count() as u16;
             let top_padding = (inner_area.height / 2).saturating_sub(text_height / 2);
 
             Layout::default()
@@ -294,13 +388,26 @@ impl<'a> Widget for Settings<'a> {
                 .split(inner_area)[1]
         };
 
-        Paragraph::new(self.network_status)
+        Paragraph::new(network_status)
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: true })
             .render(text_area, buf);
     }
 }
 
+/// Shortens `line` to at most `width` graphemes, indicating the omission with an ellipsis.
+fn truncate_with_ellipsis(line: &str, width: usize) -> String {
+    if line.graphemes(true).count() <= width {
+        return line.to_string();
+    }
+
+    line.graphemes(true)
+        .take(width.saturating_sub(1))
+        .chain(once("…"))
+        .take(width)
+        .collect()
+}
+
 #[derive(Default)]
 struct InsufficientSpaceWarning {
     have: (u16, u16),
@@ -316,8 +423,14 @@ impl InsufficientSpaceWarning {
 impl Widget for InsufficientSpaceWarning {
     fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
         let text = format!(
-            "terminal size too small\nhave {}×{}\nneed {}×{}",
-            self.have.0, self.have.1, self.need.0, self.need.1
+            "{}\n{} {}×{}\n{} {}×{}",
+            strings().terminal_too_small,
+            strings().have,
+            self.have.0,
+            self.have.1,
+            strings().need,
+            self.need.0,
+            self.need.1
         );
         let top_padding = (area.height / 2).saturating_sub(text.lines().count() as u16 / 2);
 
@@ -331,3 +444,61 @@ impl Widget for InsufficientSpaceWarning {
             .render(vertical_center, buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::buffer::Buffer;
+
+    fn clock() -> PomodoroClock<'static> {
+        PomodoroClock::new(
+            1,
+            4,
+            0.5,
+            SessionDuration(Duration::from_secs(12 * 60 + 30)),
+            Activity::Focus,
+            false,
+        )
+    }
+
+    /// The rows of the buffer as text.
+    fn rendered(clock: PomodoroClock, width: u16, height: u16) -> Vec<String> {
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        clock.render(area, &mut buf);
+
+        (0..height)
+            .map(|y| (0..width).map(|x| buf.get(x, y).symbol.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn small_areas_show_the_compact_line_instead_of_the_clock() {
+        let line = format!("{} {}", clock().duration, clock().activity_line());
+        let (line_width, _) = text_size(&line);
+
+        let rows = rendered(clock(), 40, 3);
+        assert_eq!(rows[1].trim(), line);
+        assert!(rows[0].trim().is_empty() && rows[2].trim().is_empty());
+
+        let rows = rendered(clock(), line_width, 1);
+        assert_eq!(rows, [line]);
+    }
+
+    #[test]
+    fn the_warning_is_only_shown_if_the_compact_line_does_not_fit() {
+        let line = format!("{} {}", clock().duration, clock().activity_line());
+        let (line_width, _) = text_size(&line);
+
+        let text = rendered(clock(), line_width - 1, 6).join("\n");
+        assert!(!text.contains(&line));
+        assert!(text.contains(strings().have), "{text}");
+
+        let text = rendered(clock(), 60, 20).join("\n");
+        assert!(!text.contains(strings().have), "{text}");
+        assert!(
+            !text.contains(&line),
+            "the clock has room for the full layout"
+        );
+    }
+}
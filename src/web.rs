@@ -0,0 +1,216 @@
+use crate::app::rate_limit::{Admission, RateLimiter};
+use crate::protocol::{Event, TimerVisuals};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Serves a page that shows the timer of a hosted session in the browser.
+/// Frames are pushed to the page as server-sent events, its buttons post events back to the host.
+/// The web view shuts down when it is dropped.
+pub struct WebView {
+    frame: watch::Sender<Option<TimerVisuals>>,
+    events: mpsc::Receiver<(IpAddr, Event)>,
+    /// Viewers are limited like clients, see `app::Config::client_events_per_second`.
+    rate_limiter: RateLimiter<IpAddr>,
+    task: JoinHandle<()>,
+}
+
+impl WebView {
+    pub async fn host(socket: SocketAddr, events_per_second: u32) -> io::Result<Self> {
+        let listener = TcpListener::bind(socket).await?;
+        let (frame, frame_receiver) = watch::channel(None);
+        let (event_sender, events) = mpsc::channel(16);
+        let task = tokio::spawn(serve(listener, frame_receiver, event_sender));
+
+        Ok(Self {
+            frame,
+            events,
+            rate_limiter: RateLimiter::new(events_per_second),
+            task,
+        })
+    }
+
+    pub fn update(&self, timer_visuals: &TimerVisuals) {
+        self.frame.send_if_modified(|frame| {
+            let is_modified = *frame != Some(*timer_visuals);
+            *frame = Some(*timer_visuals);
+            is_modified
+        });
+    }
+
+    /// Waits for a viewer to press a button. Viewers that press too often are ignored for the rest of the second.
+    pub async fn event(&mut self) -> Event {
+        loop {
+            let Some((viewer, event)) = self.events.recv().await else {
+                // the sender lives as long as the server task, which only ends when it is aborted
+                return std::future::pending().await;
+            };
+            let now = Instant::now();
+            self.rate_limiter.forget_idle(now);
+            match self.rate_limiter.admit(viewer, now) {
+                Admission::Allowed => return event,
+                Admission::StartedDropping => {
+                    tracing::warn!(%viewer, "web viewer sent too many events");
+                }
+                Admission::Dropped => {}
+            }
+        }
+    }
+}
+
+impl Drop for WebView {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    frame: watch::Receiver<Option<TimerVisuals>>,
+    events: mpsc::Sender<(IpAddr, Event)>,
+) {
+    loop {
+        let (stream, viewer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                tracing::debug!(%err, "failed to accept a connection");
+                continue;
+            }
+        };
+        let (frame, events) = (frame.clone(), events.clone());
+
+        // errors only affect the viewer that caused them, only log them
+        tokio::spawn(async move {
+            if let Err(err) = respond(stream, viewer.ip(), frame, events).await {
+                tracing::debug!(%err, "failed to respond to a viewer");
+            }
+        });
+    }
+}
+
+/// Longest request line and headers that are read, the rest of a longer request is ignored.
+const MAX_REQUEST_HEAD_SIZE: u64 = 8 * 1024;
+
+async fn respond(
+    mut stream: TcpStream,
+    viewer: IpAddr,
+    mut frame: watch::Receiver<Option<TimerVisuals>>,
+    events: mpsc::Sender<(IpAddr, Event)>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new((&mut stream).take(MAX_REQUEST_HEAD_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // skip the headers, none of them are needed
+    let mut header = String::new();
+    while reader.read_line(&mut header).await? > 2 {
+        header.clear();
+    }
+
+    match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/", _] => {
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{PAGE}",
+                PAGE.len()
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        ["GET", "/events", _] => {
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n")
+                .await?;
+            loop {
+                let visuals = *frame.borrow_and_update();
+                if let Some(visuals) = visuals {
+                    stream
+                        .write_all(format!("data: {}\n\n", to_json(&visuals)).as_bytes())
+                        .await?;
+                }
+                if frame.changed().await.is_err() {
+                    break;
+                }
+            }
+        }
+        ["POST", path, _] => {
+            let response = match posted_event(path) {
+                Some(event) => {
+                    let _ = events.send((viewer, event)).await;
+                    "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n"
+                }
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            };
+            stream.write_all(response.as_bytes()).await?;
+        }
+        _ => {
+            stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await?;
+        }
+    }
+
+    stream.shutdown().await
+}
+
+/// Only lets viewers do what every client can do anyway, and nothing that can't be undone.
+fn posted_event(path: &str) -> Option<Event> {
+    match path {
+        "/toggle" => Some(Event::ToggleTimer),
+        "/skip" => Some(Event::SkipActivity),
+        _ => None,
+    }
+}
+
+fn to_json(visuals: &TimerVisuals) -> String {
+    format!(
+        "{{\"time_remaining\":\"{}\",\"activity\":\"{}\",\"activity_name\":\"{}\",\"paused\":{},\"progress\":{}}}",
+        visuals.time_remaining,
+        visuals.activity.id(),
+        visuals.activity.to_string().replace('"', "\\\""),
+        visuals.timer_is_paused,
+        visuals.progress_percentage
+    )
+}
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>corrodoro</title>
+<style>
+  body { font-family: sans-serif; text-align: center; margin-top: 20vh; }
+  #time { font-size: 6em; font-variant-numeric: tabular-nums; }
+  progress { width: 20em; }
+  button { font-size: 1.2em; margin: 0.5em; }
+</style>
+</head>
+<body>
+<div id="activity">connecting…</div>
+<div id="time">--:--</div>
+<progress id="progress" max="1" value="0"></progress>
+<div>
+  <button onclick="fetch('/toggle', { method: 'POST' })">pause / resume</button>
+  <button onclick="fetch('/skip', { method: 'POST' })">skip</button>
+</div>
+<script>
+  const events = new EventSource("/events");
+  events.onmessage = (message) => {
+    const visuals = JSON.parse(message.data);
+    document.getElementById("time").textContent = visuals.time_remaining;
+    document.getElementById("activity").textContent =
+      visuals.activity_name + (visuals.paused ? " (paused)" : "");
+    document.getElementById("progress").value = visuals.progress;
+    document.title = visuals.time_remaining + " " + visuals.activity_name;
+  };
+  events.onerror = () => {
+    document.getElementById("activity").textContent = "disconnected";
+  };
+</script>
+</body>
+</html>
+"#;
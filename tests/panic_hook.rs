@@ -0,0 +1,61 @@
+//! The panic hook is global, so it is tested in a binary of its own instead of next to tests that run in parallel.
+#![cfg(unix)]
+
+#[path = "../src/tui/panic_hook.rs"]
+mod panic_hook;
+
+use crossterm::terminal;
+use panic_hook::TerminalPanicHook;
+use std::panic;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// crossterm switches the terminal on stdin to raw mode, give it one even if the tests don't run in a terminal.
+fn attach_pseudo_terminal() {
+    let (mut controller, mut device) = (0, 0);
+    let opened = unsafe {
+        libc::openpty(
+            &mut controller,
+            &mut device,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(opened, 0, "can open a pseudo terminal");
+    assert_ne!(unsafe { libc::dup2(device, libc::STDIN_FILENO) }, -1);
+}
+
+#[test]
+fn the_terminal_is_restored_before_the_previous_panic_hook_runs() {
+    static PREVIOUS_RAN: AtomicU32 = AtomicU32::new(0);
+    static RAW_MODE_IN_PREVIOUS: AtomicBool = AtomicBool::new(false);
+    attach_pseudo_terminal();
+    let original = panic::take_hook();
+    panic::set_hook(Box::new(|_| {
+        let raw_mode = terminal::is_raw_mode_enabled().expect("can query raw mode");
+        RAW_MODE_IN_PREVIOUS.store(raw_mode, Ordering::SeqCst);
+        PREVIOUS_RAN.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    for round in 1..=3 {
+        terminal::enable_raw_mode().expect("can enable raw mode");
+        let panic_hook = TerminalPanicHook::install(|| {
+            terminal::disable_raw_mode().expect("can disable raw mode");
+        });
+        assert!(panic::catch_unwind(|| panic!("while enabled")).is_err());
+        assert_eq!(PREVIOUS_RAN.load(Ordering::SeqCst), 2 * round - 1);
+        assert!(!RAW_MODE_IN_PREVIOUS.load(Ordering::SeqCst));
+        assert!(!terminal::is_raw_mode_enabled().expect("can query raw mode"));
+        panic_hook.uninstall();
+
+        // only the previous hook is left, it doesn't touch the terminal
+        terminal::enable_raw_mode().expect("can enable raw mode");
+        assert!(panic::catch_unwind(|| panic!("while disabled")).is_err());
+        assert_eq!(PREVIOUS_RAN.load(Ordering::SeqCst), 2 * round);
+        assert!(RAW_MODE_IN_PREVIOUS.load(Ordering::SeqCst));
+        terminal::disable_raw_mode().expect("can disable raw mode");
+    }
+
+    panic::set_hook(original);
+}